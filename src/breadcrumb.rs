@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use crate::level::Level;
+
+/// Something that happened before an error, such as a query or an
+/// outgoing request. See [`BloopClient::add_breadcrumb`].
+///
+/// [`BloopClient::add_breadcrumb`]: crate::BloopClient::add_breadcrumb
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    pub timestamp: i64,
+    pub category: String,
+    pub message: String,
+    pub level: Level,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl Breadcrumb {
+    pub fn new(category: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64,
+            category: category.into(),
+            message: message.into(),
+            level: Level::default(),
+            data: None,
+        }
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
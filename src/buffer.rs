@@ -1,35 +1,163 @@
-use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-/// A generic batch buffer that collects items and flushes when count triggers.
-#[allow(dead_code)]
-pub(crate) struct BatchBuffer<T: Send + 'static> {
-    items: Arc<Mutex<Vec<T>>>,
+/// Longest a push waits under [`OverflowPolicy::Block`].
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What to do with a new item when a buffer is at capacity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the incoming item.
+    DropNewest,
+    /// Discard the oldest buffered item to make room.
+    #[default]
+    DropOldest,
+    /// Block the calling thread until an in-flight upload finishes, for at
+    /// most one second.
+    ///
+    /// When nothing is in flight, or the wait times out, the oldest buffered
+    /// item is dropped instead. Capturing blocks the thread, not just the
+    /// task: from async code the upload being waited on may need that very
+    /// thread (a current-thread runtime, or a multi-thread one with every
+    /// worker blocked), in which case each capture stalls for the full
+    /// second before dropping. Prefer the dropping policies there.
+    Block,
+}
+
+/// A bounded batch buffer.
+///
+/// Items count against `capacity` from the moment they are pushed until the
+/// batch containing them has finished uploading, so batches waiting for an
+/// upload slot are bounded too.
+pub(crate) struct BatchBuffer<T> {
+    state: Mutex<State<T>>,
+    space: Condvar,
     max_size: usize,
-    tx: mpsc::UnboundedSender<Vec<T>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+struct State<T> {
+    items: Vec<T>,
+    in_flight: usize,
 }
 
-#[allow(dead_code)]
-impl<T: Send + 'static> BatchBuffer<T> {
-    pub fn new(max_size: usize, tx: mpsc::UnboundedSender<Vec<T>>) -> Self {
+impl<T> BatchBuffer<T> {
+    /// `max_size` is the batch threshold; `capacity` is raised to at least it.
+    pub fn new(max_size: usize, capacity: usize, policy: OverflowPolicy) -> Self {
         Self {
-            items: Arc::new(Mutex::new(Vec::new())),
+            state: Mutex::new(State {
+                items: Vec::new(),
+                in_flight: 0,
+            }),
+            space: Condvar::new(),
             max_size,
-            tx,
+            capacity: capacity.max(max_size),
+            policy,
+            dropped: AtomicU64::new(0),
         }
     }
 
-    pub fn push(&self, item: T) {
-        let mut items = self.items.lock().unwrap();
-        items.push(item);
-        if items.len() >= self.max_size {
-            let batch = std::mem::take(&mut *items);
-            let _ = self.tx.send(batch);
+    /// Push an item, applying the overflow policy. Returns true once the
+    /// buffer holds a full batch.
+    pub fn push(&self, item: T) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let deadline = Instant::now() + BLOCK_TIMEOUT;
+        while state.items.len() + state.in_flight >= self.capacity {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.policy {
+                OverflowPolicy::Block if state.in_flight > 0 && !remaining.is_zero() => {
+                    state = self.space.wait_timeout(state, remaining).unwrap().0;
+                }
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                _ if state.items.is_empty() => {
+                    // Everything is in flight; there is nothing older to drop.
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                _ => {
+                    state.items.remove(0);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        state.items.push(item);
+        state.items.len() >= self.max_size
+    }
+
+    /// Offer each buffered item, newest first, to `merge` until it returns
+    /// true. Returns whether any item was merged into.
+    pub fn merge(&self, merge: impl FnMut(&mut T) -> bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.items.iter_mut().rev().any(merge)
+    }
+
+    /// Take everything buffered as one batch. The items keep counting
+    /// against capacity until the returned guard is dropped.
+    pub fn take(self: &Arc<Self>) -> (Vec<T>, InFlight<T>) {
+        let mut state = self.state.lock().unwrap();
+        let batch = std::mem::take(&mut state.items);
+        state.in_flight += batch.len();
+        let guard = InFlight {
+            buffer: self.clone(),
+            len: batch.len(),
+        };
+        (batch, guard)
+    }
+
+    /// Put a batch that could not be sent back in front of the buffered
+    /// items, releasing its in-flight capacity. Items beyond capacity are
+    /// dropped per the overflow policy.
+    pub fn requeue(&self, mut items: Vec<T>, mut in_flight: InFlight<T>) {
+        let len = std::mem::take(&mut in_flight.len);
+        drop(in_flight);
+
+        let mut state = self.state.lock().unwrap();
+        state.in_flight = state.in_flight.saturating_sub(len);
+        items.append(&mut state.items);
+        state.items = items;
+        let excess = (state.items.len() + state.in_flight).saturating_sub(self.capacity);
+        let excess = excess.min(state.items.len());
+        if excess > 0 {
+            if self.policy == OverflowPolicy::DropNewest {
+                let keep = state.items.len() - excess;
+                state.items.truncate(keep);
+            } else {
+                state.items.drain(..excess);
+            }
+            self.dropped.fetch_add(excess as u64, Ordering::Relaxed);
         }
+        self.space.notify_all();
+    }
+
+    /// Items buffered or in flight.
+    pub fn pending(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.items.len() + state.in_flight
     }
 
-    pub fn drain(&self) -> Vec<T> {
-        let mut items = self.items.lock().unwrap();
-        std::mem::take(&mut *items)
+    /// Number of items discarded by the overflow policy.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Capacity held by a batch taken from a [`BatchBuffer`], released on drop
+/// so an abandoned upload cannot leak it.
+pub(crate) struct InFlight<T> {
+    buffer: Arc<BatchBuffer<T>>,
+    len: usize,
+}
+
+impl<T> Drop for InFlight<T> {
+    fn drop(&mut self) {
+        let mut state = self.buffer.state.lock().unwrap();
+        state.in_flight = state.in_flight.saturating_sub(self.len);
+        self.buffer.space.notify_all();
     }
 }
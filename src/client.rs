@@ -1,11 +1,31 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use crate::event::{Event, IngestEvent};
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use tokio::sync::{Notify, Semaphore};
+use crate::buffer::{BatchBuffer, InFlight, OverflowPolicy};
+use crate::compression::Compression;
+use crate::error::BloopError;
+use crate::event::{fingerprint, hash_parts, Event, IngestEvent};
+use crate::level::Level;
+use crate::rate_limit::{RateLimiter, SUPPRESSED_ERROR_TYPE};
+use crate::report::FlushReport;
+use crate::retry::RetryPolicy;
+use crate::sampling::{sampled, Sampling, SamplingContext};
+use crate::scope::Scope;
+use crate::scrub::Scrubber;
 use crate::signing;
+use crate::spool::{BatchKind, Spool};
+use crate::stack::parse_frames;
+use crate::transport::{
+    HttpTransport, NoopTransport, SendOutcome, Transport, DEFAULT_CONNECT_TIMEOUT,
+    DEFAULT_REQUEST_TIMEOUT, DEFAULT_USER_AGENT,
+};
 
 #[cfg(feature = "tracing")]
 use crate::tracing::Trace;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct BloopClientBuilder {
     endpoint: Option<String>,
     project_key: Option<String>,
@@ -13,6 +33,55 @@ pub struct BloopClientBuilder {
     release: String,
     source: String,
     max_buffer_size: usize,
+    sampling: Sampling,
+    enabled: bool,
+    max_queue_size: usize,
+    overflow_policy: OverflowPolicy,
+    max_in_flight: usize,
+    retry: RetryPolicy,
+    compression: Compression,
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    proxy: Option<String>,
+    root_certificates: Vec<Vec<u8>>,
+    user_agent: String,
+    http_client: Option<reqwest::Client>,
+    spool: Option<(PathBuf, u64)>,
+    flush_interval: Option<Duration>,
+    transport: Option<Arc<dyn Transport>>,
+    in_app_prefixes: Vec<String>,
+    dedup_window: Option<Duration>,
+    rate_limit: Option<(u32, Duration)>,
+    before_send_event: Option<BeforeSend<IngestEvent>>,
+    #[cfg(feature = "tracing")]
+    before_send_trace: Option<BeforeSend<Trace>>,
+    scrubber: Option<Scrubber>,
+    user_id_salt: Option<String>,
+    max_breadcrumbs: usize,
+    min_level: Level,
+}
+
+/// Error type of events from [`BloopClient::capture_message`].
+pub const MESSAGE_ERROR_TYPE: &str = "Message";
+
+/// Hook that edits an item before it is buffered, or drops it.
+type BeforeSend<T> = Arc<dyn Fn(T) -> Option<T> + Send + Sync>;
+
+impl std::fmt::Debug for BloopClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BloopClientBuilder")
+            .field("endpoint", &self.endpoint)
+            .field("environment", &self.environment)
+            .field("source", &self.source)
+            .field("custom_transport", &self.transport.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for BloopClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BloopClientBuilder {
@@ -24,6 +93,32 @@ impl BloopClientBuilder {
             release: String::new(),
             source: "rust".into(),
             max_buffer_size: 20,
+            sampling: Sampling::default(),
+            enabled: true,
+            max_queue_size: 1000,
+            overflow_policy: OverflowPolicy::default(),
+            max_in_flight: 4,
+            retry: RetryPolicy::default(),
+            compression: Compression::default(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            proxy: None,
+            root_certificates: Vec::new(),
+            user_agent: DEFAULT_USER_AGENT.into(),
+            http_client: None,
+            spool: None,
+            flush_interval: None,
+            transport: None,
+            in_app_prefixes: Vec::new(),
+            dedup_window: None,
+            rate_limit: None,
+            before_send_event: None,
+            #[cfg(feature = "tracing")]
+            before_send_trace: None,
+            scrubber: None,
+            user_id_salt: None,
+            max_breadcrumbs: 100,
+            min_level: Level::Debug,
         }
     }
 
@@ -52,31 +147,383 @@ impl BloopClientBuilder {
         self
     }
 
-    pub fn build(self) -> Result<BloopClient, String> {
-        let endpoint = self.endpoint.ok_or("endpoint is required")?;
-        let project_key = self.project_key.ok_or("project_key is required")?;
+    /// Number of buffered events (or traces) that triggers an upload.
+    pub fn max_buffer_size(mut self, size: usize) -> Self {
+        self.max_buffer_size = size.max(1);
+        self
+    }
+
+    /// Fraction of captured errors to keep, from 0.0 to 1.0.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sampling.errors = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Fraction of traces to keep, from 0.0 to 1.0.
+    pub fn traces_sample_rate(mut self, rate: f64) -> Self {
+        self.sampling.traces = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sample rate for errors of one type, overriding `sample_rate`.
+    pub fn error_type_sample_rate(mut self, error_type: impl Into<String>, rate: f64) -> Self {
+        self.sampling
+            .error_types
+            .insert(error_type.into(), rate.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Sample rate for traces with one name, overriding `traces_sample_rate`.
+    pub fn trace_name_sample_rate(mut self, name: impl Into<String>, rate: f64) -> Self {
+        self.sampling
+            .trace_names
+            .insert(name.into(), rate.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Decide sample rates in code. Returning `None` falls back to the
+    /// per-type or per-name overrides and then the global rates.
+    pub fn sampler<F>(mut self, sampler: F) -> Self
+    where
+        F: Fn(&SamplingContext<'_>) -> Option<f64> + Send + Sync + 'static,
+    {
+        self.sampling.sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    /// A disabled client builds without an endpoint or key and discards
+    /// everything it is given.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Maximum number of events (and, separately, traces) held in memory,
+    /// counting both buffered items and batches not yet uploaded. What
+    /// happens beyond it is set by `overflow_policy`.
+    pub fn max_queue_size(mut self, size: usize) -> Self {
+        self.max_queue_size = size.max(1);
+        self
+    }
+
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Maximum number of batch uploads running at once.
+    pub fn max_in_flight(mut self, uploads: usize) -> Self {
+        self.max_in_flight = uploads.max(1);
+        self
+    }
+
+    /// Retry policy applied to failed batch uploads.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Compress batch request bodies. Needs the `gzip` or `zstd` feature.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Timeout for establishing a connection. Defaults to 10 seconds.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout for a whole request, from connecting until the response
+    /// body has been read. Defaults to 30 seconds.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Send all requests through this HTTP(S) proxy.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Trust additional PEM-encoded root certificates (one or a bundle).
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Use a pre-configured HTTP client. The timeout, proxy, certificate and
+    /// user-agent settings above are then ignored.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
 
-        let http = reqwest::Client::new();
-        let error_buffer = Arc::new(Mutex::new(Vec::new()));
+    /// Spool pending batches to `dir` so they survive crashes and failed
+    /// uploads. Files left over from a previous run are replayed by `build()`,
+    /// and batches whose upload failed transiently (network errors, 5xx) are
+    /// replayed by the next flush. Batches the server rejected stay on disk
+    /// until a later run replays them. The oldest files are evicted once the
+    /// spool exceeds `max_bytes`.
+    pub fn spool(mut self, dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        self.spool = Some((dir.into(), max_bytes));
+        self
+    }
+
+    /// Flush from a background task every `interval`, in addition to
+    /// whenever a buffer reaches its size threshold. Requires `build()` to be
+    /// called inside a tokio runtime; stop the task with `shutdown()`.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+
+    /// Deliver batches through a custom transport instead of HTTP. The
+    /// endpoint, project key and compression settings are then unused.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Module prefixes (usually crate names) whose stack frames are marked
+    /// in-app. Without any, every frame outside the standard library and the
+    /// SDK's own dependencies is.
+    pub fn in_app_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.in_app_prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Collapse repeats of a still-buffered event with the same fingerprint
+    /// into it, counting them in `occurrences`, for up to `window` after
+    /// the first one.
+    pub fn dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Limit each fingerprint to `burst` events, refilling at `burst` per
+    /// `period`. How many were suppressed is reported once per `period`, and
+    /// on flush, as an [`SUPPRESSED_ERROR_TYPE`] event.
+    ///
+    /// [`SUPPRESSED_ERROR_TYPE`]: crate::SUPPRESSED_ERROR_TYPE
+    pub fn rate_limit(mut self, burst: u32, period: Duration) -> Self {
+        self.rate_limit = Some((burst, period));
+        self
+    }
+
+    /// Called with every event before it is buffered, including summaries
+    /// of rate-limited events. Return the (possibly edited) event to keep
+    /// it, or `None` to drop it. Dedup and rate limiting see the result.
+    pub fn before_send_event<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(IngestEvent) -> Option<IngestEvent> + Send + Sync + 'static,
+    {
+        self.before_send_event = Some(Arc::new(hook));
+        self
+    }
+
+    /// Called with every sampled trace before it is buffered. Return the
+    /// (possibly edited) trace to keep it, or `None` to drop it.
+    #[cfg(feature = "tracing")]
+    pub fn before_send_trace<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(Trace) -> Option<Trace> + Send + Sync + 'static,
+    {
+        self.before_send_trace = Some(Arc::new(hook));
+        self
+    }
+
+    /// Redact personal data and secrets from events and traces after the
+    /// `before_send` hooks and before buffering.
+    pub fn scrubber(mut self, scrubber: Scrubber) -> Self {
+        self.scrubber = Some(scrubber);
+        self
+    }
+
+    /// Salt for [`BloopClient::hash_user_id`]. Defaults to the project key,
+    /// so every service reporting to a project hashes ids the same way.
+    pub fn user_id_salt(mut self, salt: impl Into<String>) -> Self {
+        self.user_id_salt = Some(salt.into());
+        self
+    }
+
+    /// Breadcrumbs kept per scope and attached to each event.
+    pub fn max_breadcrumbs(mut self, max: usize) -> Self {
+        self.max_breadcrumbs = max;
+        self
+    }
+
+    /// Drop events below this level before sampling. Defaults to
+    /// [`Level::Debug`], which keeps everything.
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    fn build_http_client(&self) -> Result<reqwest::Client, BloopError> {
+        let invalid = |setting, reason: String| BloopError::InvalidConfig { setting, reason };
+
+        let mut http = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .user_agent(&self.user_agent);
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| invalid("proxy", e.to_string()))?;
+            http = http.proxy(proxy);
+        }
+        for pem in &self.root_certificates {
+            let certs = reqwest::Certificate::from_pem_bundle(pem)
+                .map_err(|e| invalid("root certificate", e.to_string()))?;
+            if certs.is_empty() {
+                return Err(invalid("root certificate", "no PEM certificates found".into()));
+            }
+            for cert in certs {
+                http = http.add_root_certificate(cert);
+            }
+        }
+        http.build().map_err(|e| invalid("http client", e.to_string()))
+    }
+
+    pub fn build(self) -> Result<BloopClient, BloopError> {
+        if let Some(endpoint) = &self.endpoint {
+            validate_endpoint(endpoint)?;
+        }
+
+        let transport: Arc<dyn Transport> = match self.transport {
+            Some(transport) => transport,
+            None if !self.enabled => Arc::new(NoopTransport),
+            None => {
+                let endpoint = self.endpoint.clone().ok_or(BloopError::MissingConfig("endpoint"))?;
+                let project_key = self
+                    .project_key
+                    .clone()
+                    .ok_or(BloopError::MissingConfig("project_key"))?;
+                let http = match self.http_client.clone() {
+                    Some(http) => http,
+                    None => self.build_http_client()?,
+                };
+                Arc::new(
+                    HttpTransport::with_client(http, endpoint, project_key)
+                        .compression(self.compression),
+                )
+            }
+        };
+
+        let spool = match self.spool.filter(|_| self.enabled) {
+            Some((dir, max_bytes)) => Some(Arc::new(Spool::open(&dir, max_bytes).map_err(
+                |e| BloopError::InvalidConfig {
+                    setting: "spool",
+                    reason: format!("{}: {e}", dir.display()),
+                },
+            )?)),
+            None => None,
+        };
+
+        let uploader = Uploader {
+            transport,
+            retry: self.retry,
+            paused_until: Arc::new(Mutex::new(None)),
+            slots: Arc::new(Semaphore::new(self.max_in_flight)),
+            spool,
+        };
+
+        // Replay leftovers now if we can; otherwise the next flush does it.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let uploader = uploader.clone();
+            handle.spawn(async move { uploader.replay_spool(&mut FlushReport::default()).await });
+        }
+        let error_buffer = Arc::new(BatchBuffer::new(
+            self.max_buffer_size,
+            self.max_queue_size,
+            self.overflow_policy,
+        ));
 
         #[cfg(feature = "tracing")]
-        let trace_buffer = Arc::new(Mutex::new(Vec::<Trace>::new()));
+        let trace_buffer = Arc::new(BatchBuffer::<Trace>::new(
+            self.max_buffer_size,
+            self.max_queue_size,
+            self.overflow_policy,
+        ));
+
+        let worker = match self.flush_interval.filter(|_| self.enabled) {
+            Some(interval) => {
+                let handle = tokio::runtime::Handle::try_current().map_err(|_| {
+                    BloopError::InvalidConfig {
+                        setting: "flush_interval",
+                        reason: "requires a tokio runtime".into(),
+                    }
+                })?;
+                Some(Worker::spawn(
+                    &handle,
+                    interval,
+                    uploader.clone(),
+                    error_buffer.clone(),
+                    #[cfg(feature = "tracing")]
+                    trace_buffer.clone(),
+                ))
+            }
+            None => None,
+        };
 
         Ok(BloopClient {
-            endpoint: endpoint.trim_end_matches('/').to_string(),
-            project_key,
+            endpoint: self.endpoint,
             environment: self.environment,
             release: self.release,
             source: self.source,
-            max_buffer_size: self.max_buffer_size,
-            http,
+            sampling: self.sampling,
+            enabled: self.enabled,
+            in_app_prefixes: self.in_app_prefixes,
+            dedup_window: self.dedup_window,
+            rate_limiter: self
+                .rate_limit
+                .map(|(burst, period)| RateLimiter::new(burst, period)),
+            before_send_event: self.before_send_event,
+            #[cfg(feature = "tracing")]
+            before_send_trace: self.before_send_trace,
+            scrubber: self.scrubber,
+            user_id_salt: self
+                .user_id_salt
+                .or(self.project_key)
+                .unwrap_or_default(),
+            scope: Mutex::new(Scope::default()),
+            max_breadcrumbs: self.max_breadcrumbs,
+            min_level: self.min_level,
+            uploader,
             error_buffer,
             #[cfg(feature = "tracing")]
             trace_buffer,
+            worker,
         })
     }
 }
 
+/// Check that an endpoint is an absolute http(s) URL with a host.
+fn validate_endpoint(endpoint: &str) -> Result<(), BloopError> {
+    let invalid = |reason: &str| BloopError::InvalidEndpoint {
+        url: endpoint.to_string(),
+        reason: reason.to_string(),
+    };
+    let url = reqwest::Url::parse(endpoint).map_err(|e| invalid(&e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("scheme must be http or https"));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("missing host"));
+    }
+    Ok(())
+}
+
 impl std::fmt::Debug for BloopClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BloopClient")
@@ -88,16 +535,36 @@ impl std::fmt::Debug for BloopClient {
 }
 
 pub struct BloopClient {
-    endpoint: String,
-    project_key: String,
+    endpoint: Option<String>,
     environment: String,
     release: String,
     source: String,
-    max_buffer_size: usize,
-    http: reqwest::Client,
-    error_buffer: Arc<Mutex<Vec<IngestEvent>>>,
+    sampling: Sampling,
+    enabled: bool,
+    in_app_prefixes: Vec<String>,
+    dedup_window: Option<Duration>,
+    rate_limiter: Option<RateLimiter>,
+    before_send_event: Option<BeforeSend<IngestEvent>>,
+    #[cfg(feature = "tracing")]
+    before_send_trace: Option<BeforeSend<Trace>>,
+    scrubber: Option<Scrubber>,
+    user_id_salt: String,
+    pub(crate) scope: Mutex<Scope>,
+    pub(crate) max_breadcrumbs: usize,
+    min_level: Level,
+    uploader: Uploader,
+    error_buffer: Arc<BatchBuffer<IngestEvent>>,
     #[cfg(feature = "tracing")]
-    trace_buffer: Arc<Mutex<Vec<Trace>>>,
+    trace_buffer: Arc<BatchBuffer<Trace>>,
+    worker: Option<Worker>,
+}
+
+impl Drop for BloopClient {
+    fn drop(&mut self) {
+        if let Some(worker) = &self.worker {
+            worker.stop.notify_one();
+        }
+    }
 }
 
 impl BloopClient {
@@ -107,41 +574,178 @@ impl BloopClient {
 
     /// Capture a structured error event.
     pub fn capture(&self, event: Event) {
+        if !self.accepts(&event) {
+            return;
+        }
+        self.report_suppressed(false);
+        if let Some(ingest) = self.prepare_event(event, Some(&self.current_scope()), true) {
+            self.push_event(ingest);
+        }
+    }
+
+    /// Capture into the buffer only, never starting an upload and never
+    /// blocking on the global scope. For the panic hook, which may run
+    /// outside a runtime or while the scope lock is held, and flushes itself.
+    pub(crate) fn capture_buffered(&self, event: Event) {
+        if !self.accepts(&event) {
+            return;
+        }
+        if let Some(ingest) = self.prepare_event(event, Some(&self.try_current_scope()), true) {
+            self.error_buffer.push(ingest);
+        }
+    }
+
+    /// Whether an event passes the enabled flag, minimum level and sampling.
+    fn accepts(&self, event: &Event) -> bool {
+        self.enabled
+            && event.level.unwrap_or(Level::Error) >= self.min_level
+            && sampled(self.sampling.error_rate(event))
+    }
+
+    /// Turn an event into an `IngestEvent` with `scope` applied, running the
+    /// hooks, dedup and, when `limited`, rate limiting. Returns `None` when
+    /// the event was dropped or merged into a buffered one.
+    fn prepare_event(
+        &self,
+        event: Event,
+        scope: Option<&Scope>,
+        limited: bool,
+    ) -> Option<IngestEvent> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_millis() as i64;
 
-        let ingest = IngestEvent {
+        let frames = event
+            .stack
+            .as_deref()
+            .map(|stack| parse_frames(stack, &self.in_app_prefixes))
+            .filter(|frames| !frames.is_empty());
+        let mut ingest = IngestEvent {
             timestamp: now,
             source: event.source.unwrap_or_else(|| self.source.clone()),
             environment: self.environment.clone(),
             release: self.release.clone(),
             error_type: event.error_type,
             message: event.message,
+            level: event.level.unwrap_or(Level::Error),
             route_or_procedure: event.route_or_procedure,
             screen: event.screen,
             stack: event.stack,
+            frames,
             http_status: event.http_status,
             request_id: event.request_id,
             user_id_hash: event.user_id_hash,
             metadata: event.metadata,
+            fingerprint: String::new(),
+            occurrences: 1,
+            redactions: Default::default(),
+            breadcrumbs: Vec::new(),
         };
+        if let Some(scope) = scope {
+            scope.apply(&mut ingest);
+        }
+        ingest.fingerprint = event.fingerprint.unwrap_or_else(|| {
+            fingerprint(
+                &ingest.error_type,
+                ingest.route_or_procedure.as_deref(),
+                ingest.frames.as_deref().unwrap_or_default(),
+            )
+        });
+        let ingest = match &self.before_send_event {
+            Some(hook) => hook(ingest),
+            None => Some(ingest),
+        };
+        let mut ingest = ingest?;
+        if let Some(scrubber) = &self.scrubber {
+            scrubber.scrub_event(&mut ingest);
+        }
+
+        if let Some(window) = self.dedup_window {
+            let window = window.as_millis() as i64;
+            let merged = self.error_buffer.merge(|buffered| {
+                let repeat = buffered.fingerprint == ingest.fingerprint
+                    && ingest.timestamp - buffered.timestamp < window;
+                if repeat {
+                    buffered.occurrences += 1;
+                }
+                repeat
+            });
+            if merged {
+                return None;
+            }
+        }
 
-        let mut buf = self.error_buffer.lock().unwrap();
-        buf.push(ingest);
-        if buf.len() >= self.max_buffer_size {
-            let batch = std::mem::take(&mut *buf);
-            drop(buf);
-            let client = self.http.clone();
-            let endpoint = self.endpoint.clone();
-            let key = self.project_key.clone();
+        if let Some(limiter) = self.rate_limiter.as_ref().filter(|_| limited) {
+            if !limiter.allow(&ingest.fingerprint, &ingest.error_type) {
+                return None;
+            }
+        }
+        Some(ingest)
+    }
+
+    /// Buffer an event, starting an upload if that fills a batch.
+    fn push_event(&self, ingest: IngestEvent) {
+        if let Some((batch, in_flight)) = self.enqueue(&self.error_buffer, ingest) {
+            let (uploader, buffer) = (self.uploader.clone(), self.error_buffer.clone());
             tokio::spawn(async move {
-                let _ = send_error_batch(&client, &endpoint, &key, batch).await;
+                let batch = Batch::Events(batch);
+                let limited = matches!(
+                    uploader.send(&batch).await,
+                    Err(BloopError::RateLimited { .. })
+                );
+                if let (true, Batch::Events(events)) = (limited, batch) {
+                    buffer.requeue(events, in_flight);
+                }
             });
         }
     }
 
+    /// Capture a summary of rate-limited events if one is due, or right away
+    /// with `force`.
+    fn report_suppressed(&self, force: bool) {
+        let Some(limiter) = &self.rate_limiter else {
+            return;
+        };
+        let suppressed = limiter.take_suppressed(force);
+        if suppressed.is_empty() {
+            return;
+        }
+
+        let total: u64 = suppressed.iter().map(|s| s.count).sum();
+        let entries: Vec<_> = suppressed
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "fingerprint": s.fingerprint,
+                    "error_type": s.error_type,
+                    "count": s.count,
+                })
+            })
+            .collect();
+        let event = Event {
+            error_type: SUPPRESSED_ERROR_TYPE.into(),
+            level: Some(Level::Warning),
+            message: format!(
+                "suppressed {total} rate-limited events ({} fingerprints)",
+                suppressed.len()
+            ),
+            metadata: Some(serde_json::json!({ "suppressed": entries })),
+            ..Default::default()
+        };
+        if let Some(ingest) = self.prepare_event(event, None, false) {
+            self.push_event(ingest);
+        }
+    }
+
+    /// Hash a user identifier for `user_id_hash`: hex HMAC-SHA256 keyed with
+    /// the configured salt, over a fixed prefix and the id. The prefix keeps
+    /// the hash distinct from a request signature when the salt defaults to
+    /// the project key.
+    pub fn hash_user_id(&self, user_id: &str) -> String {
+        signing::hash_user_id(&self.user_id_salt, user_id)
+    }
+
     /// Capture an error type and message.
     pub fn capture_error(&self, error_type: impl Into<String>, message: impl Into<String>) {
         self.capture(Event {
@@ -151,94 +755,473 @@ impl BloopClient {
         });
     }
 
+    /// Capture a plain message at `level`, with error type
+    /// [`MESSAGE_ERROR_TYPE`]. Messages are fingerprinted by their text, so
+    /// different messages are never deduplicated together.
+    ///
+    /// [`MESSAGE_ERROR_TYPE`]: crate::MESSAGE_ERROR_TYPE
+    pub fn capture_message(&self, level: Level, message: impl Into<String>) {
+        let message = message.into();
+        self.capture(Event {
+            error_type: MESSAGE_ERROR_TYPE.into(),
+            fingerprint: Some(hash_parts([MESSAGE_ERROR_TYPE, message.as_str()])),
+            message,
+            level: Some(level),
+            ..Default::default()
+        });
+    }
+
+    /// Capture an error value along with its `source()` chain.
+    /// See [`Event::from_error`] for how it is converted.
+    pub fn capture_std_error<E: std::error::Error + ?Sized>(&self, error: &E) {
+        self.capture(Event::from_error(error));
+    }
+
+    /// Start a trace. The sampling decision is made here, from the trace
+    /// name, and recorded on the trace; unsampled traces are not sent.
     #[cfg(feature = "tracing")]
     pub fn start_trace(&self, name: impl Into<String>) -> crate::tracing::Trace {
-        crate::tracing::Trace::new(name)
+        let mut trace = crate::tracing::Trace::new(name);
+        self.sample_trace(&mut trace);
+        trace
     }
 
     #[cfg(feature = "tracing")]
-    pub fn send_trace(&self, trace: Trace) {
-        let mut buf = self.trace_buffer.lock().unwrap();
-        buf.push(trace);
-        if buf.len() >= self.max_buffer_size {
-            let batch = std::mem::take(&mut *buf);
-            drop(buf);
-            let client = self.http.clone();
-            let endpoint = self.endpoint.clone();
-            let key = self.project_key.clone();
+    pub fn send_trace(&self, mut trace: Trace) {
+        // Traces built with `Trace::new` are sampled on the way out.
+        if trace.sample_rate.is_none() {
+            self.sample_trace(&mut trace);
+        }
+        if !self.enabled || !trace.sampled {
+            return;
+        }
+        let trace = match &self.before_send_trace {
+            Some(hook) => hook(trace),
+            None => Some(trace),
+        };
+        let Some(mut trace) = trace else {
+            return;
+        };
+        if let Some(scrubber) = &self.scrubber {
+            scrubber.scrub_trace(&mut trace);
+        }
+
+        if let Some((batch, in_flight)) = self.enqueue(&self.trace_buffer, trace) {
+            let (uploader, buffer) = (self.uploader.clone(), self.trace_buffer.clone());
             tokio::spawn(async move {
-                let _ = send_trace_batch(&client, &endpoint, &key, batch).await;
+                let batch = Batch::Traces(batch);
+                let limited = matches!(
+                    uploader.send(&batch).await,
+                    Err(BloopError::RateLimited { .. })
+                );
+                if let (true, Batch::Traces(traces)) = (limited, batch) {
+                    buffer.requeue(traces, in_flight);
+                }
             });
         }
     }
 
-    /// Flush all buffered events and traces.
-    pub async fn flush(&self) {
-        // Flush errors
-        let errors = {
-            let mut buf = self.error_buffer.lock().unwrap();
-            std::mem::take(&mut *buf)
-        };
-        if !errors.is_empty() {
-            let _ = send_error_batch(&self.http, &self.endpoint, &self.project_key, errors).await;
+    #[cfg(feature = "tracing")]
+    fn sample_trace(&self, trace: &mut Trace) {
+        let rate = self.sampling.trace_rate(&trace.name);
+        trace.sample_rate = Some(rate);
+        trace.sampled = sampled(rate);
+    }
+
+    /// Buffer an item, returning a batch to upload once the buffer is full.
+    ///
+    /// While the server has paused uploads nothing is returned and the buffer
+    /// keeps filling up to its capacity. With a background worker the worker
+    /// is woken instead.
+    fn enqueue<T>(&self, buffer: &Arc<BatchBuffer<T>>, item: T) -> Option<(Vec<T>, InFlight<T>)> {
+        if !buffer.push(item) || self.uploader.is_paused() {
+            return None;
         }
+        if let Some(worker) = &self.worker {
+            worker.wake.notify_one();
+            return None;
+        }
+        Some(buffer.take())
+    }
 
-        // Flush traces
+    /// Number of events and traces discarded because a buffer was full.
+    pub fn dropped_count(&self) -> u64 {
+        let dropped = self.error_buffer.dropped();
         #[cfg(feature = "tracing")]
-        {
-            let traces = {
-                let mut buf = self.trace_buffer.lock().unwrap();
-                std::mem::take(&mut *buf)
-            };
-            if !traces.is_empty() {
-                let _ = send_trace_batch(&self.http, &self.endpoint, &self.project_key, traces).await;
+        let dropped = dropped + self.trace_buffer.dropped();
+        dropped
+    }
+
+    /// Events and traces buffered or being uploaded.
+    fn pending(&self) -> usize {
+        let pending = self.error_buffer.pending();
+        #[cfg(feature = "tracing")]
+        let pending = pending + self.trace_buffer.pending();
+        pending
+    }
+
+    /// Flush all buffered events and traces, reporting what was delivered.
+    ///
+    /// If the server has paused uploads, nothing is sent and the buffered
+    /// items are reported as pending.
+    pub async fn flush(&self) -> FlushReport {
+        let mut report = FlushReport::default();
+        self.flush_into(&mut report).await;
+        report.pending += self.pending();
+        report
+    }
+
+    /// Stop the background worker, if any, then flush.
+    pub async fn shutdown(&self) -> FlushReport {
+        let mut report = FlushReport::default();
+        self.shutdown_into(&mut report).await;
+        report.pending += self.pending();
+        report
+    }
+
+    /// Like `shutdown`, but gives up once `timeout` has elapsed. Batches cut
+    /// off mid-upload are counted as pending (and stay in the spool, if any).
+    pub async fn shutdown_timeout(&self, timeout: Duration) -> FlushReport {
+        let mut report = FlushReport::default();
+        let finished = tokio::time::timeout(timeout, self.shutdown_into(&mut report))
+            .await
+            .is_ok();
+        report.timed_out = !finished;
+        report.pending += self.pending();
+        report
+    }
+
+    async fn flush_into(&self, report: &mut FlushReport) {
+        self.report_suppressed(true);
+        flush_buffers(
+            &self.uploader,
+            &self.error_buffer,
+            #[cfg(feature = "tracing")]
+            &self.trace_buffer,
+            report,
+        )
+        .await;
+    }
+
+    async fn shutdown_into(&self, report: &mut FlushReport) {
+        if let Some(worker) = &self.worker {
+            worker.stop.notify_one();
+            let handle = worker.handle.lock().unwrap().take();
+            if let Some(handle) = handle {
+                let _ = handle.await;
             }
         }
+        self.flush_into(report).await;
     }
+}
+
+/// Background task that flushes the buffers on a timer or when woken.
+struct Worker {
+    wake: Arc<Notify>,
+    stop: Arc<Notify>,
+    handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
 
-    /// Flush and shutdown.
-    pub async fn shutdown(&self) {
-        self.flush().await;
+impl Worker {
+    fn spawn(
+        runtime: &tokio::runtime::Handle,
+        interval: Duration,
+        uploader: Uploader,
+        error_buffer: Arc<BatchBuffer<IngestEvent>>,
+        #[cfg(feature = "tracing")] trace_buffer: Arc<BatchBuffer<Trace>>,
+    ) -> Self {
+        let wake = Arc::new(Notify::new());
+        let stop = Arc::new(Notify::new());
+
+        let (woken, stopped) = (wake.clone(), stop.clone());
+        let handle = runtime.spawn(async move {
+            let start = tokio::time::Instant::now() + interval;
+            let mut ticker = tokio::time::interval_at(start, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = stopped.notified() => return,
+                    _ = woken.notified() => {}
+                    _ = ticker.tick() => {}
+                }
+                flush_buffers(
+                    &uploader,
+                    &error_buffer,
+                    #[cfg(feature = "tracing")]
+                    &trace_buffer,
+                    &mut FlushReport::default(),
+                )
+                .await;
+            }
+        });
+
+        Self {
+            wake,
+            stop,
+            handle: Mutex::new(Some(handle)),
+        }
     }
 }
 
-async fn send_error_batch(
-    http: &reqwest::Client,
-    endpoint: &str,
-    key: &str,
-    events: Vec<IngestEvent>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let body = serde_json::to_vec(&serde_json::json!({ "events": events }))?;
-    let signature = signing::sign(key, &body);
-
-    http.post(format!("{endpoint}/v1/ingest/batch"))
-        .header("Content-Type", "application/json")
-        .header("X-Signature", signature)
-        .header("X-Project-Key", key)
-        .body(body)
-        .send()
-        .await?;
+/// Replay the spool, then send everything currently buffered.
+///
+/// Gives up while the server has paused uploads, leaving the rest buffered.
+async fn flush_buffers(
+    uploader: &Uploader,
+    error_buffer: &Arc<BatchBuffer<IngestEvent>>,
+    #[cfg(feature = "tracing")] trace_buffer: &Arc<BatchBuffer<Trace>>,
+    report: &mut FlushReport,
+) {
+    if uploader.is_paused() {
+        return;
+    }
+    uploader.replay_spool(report).await;
 
-    Ok(())
+    // Flush errors
+    if uploader.is_paused() {
+        return;
+    }
+    let (errors, in_flight) = error_buffer.take();
+    if !errors.is_empty() {
+        let batch = Batch::Events(errors);
+        let paused = uploader.send_reported(&batch, report).await;
+        if let (true, Batch::Events(events)) = (paused, batch) {
+            error_buffer.requeue(events, in_flight);
+        }
+    }
+
+    // Flush traces
+    #[cfg(feature = "tracing")]
+    if !uploader.is_paused() {
+        let (traces, in_flight) = trace_buffer.take();
+        if !traces.is_empty() {
+            let batch = Batch::Traces(traces);
+            let paused = uploader.send_reported(&batch, report).await;
+            if let (true, Batch::Traces(traces)) = (paused, batch) {
+                trace_buffer.requeue(traces, in_flight);
+            }
+        }
+    }
+}
+
+/// A batch on its way to the transport.
+enum Batch {
+    Events(Vec<IngestEvent>),
+    #[cfg(feature = "tracing")]
+    Traces(Vec<Trace>),
+}
+
+#[derive(Deserialize)]
+struct EventEnvelope {
+    events: Vec<IngestEvent>,
 }
 
 #[cfg(feature = "tracing")]
-async fn send_trace_batch(
-    http: &reqwest::Client,
-    endpoint: &str,
-    key: &str,
+#[derive(Deserialize)]
+struct TraceEnvelope {
     traces: Vec<Trace>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let body = serde_json::to_vec(&serde_json::json!({ "traces": traces }))?;
-    let signature = signing::sign(key, &body);
-
-    http.post(format!("{endpoint}/v1/traces/batch"))
-        .header("Content-Type", "application/json")
-        .header("X-Signature", signature)
-        .header("X-Project-Key", key)
-        .body(body)
-        .send()
-        .await?;
+}
 
-    Ok(())
+impl Batch {
+    fn len(&self) -> usize {
+        match self {
+            Batch::Events(events) => events.len(),
+            #[cfg(feature = "tracing")]
+            Batch::Traces(traces) => traces.len(),
+        }
+    }
+
+    fn kind(&self) -> BatchKind {
+        match self {
+            Batch::Events(_) => BatchKind::Events,
+            #[cfg(feature = "tracing")]
+            Batch::Traces(_) => BatchKind::Traces,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        match self {
+            Batch::Events(events) => serde_json::to_vec(&serde_json::json!({ "events": events })),
+            #[cfg(feature = "tracing")]
+            Batch::Traces(traces) => serde_json::to_vec(&serde_json::json!({ "traces": traces })),
+        }
+    }
+
+    /// Decode a spooled batch. Trace files are skipped without the
+    /// `tracing` feature and stay on disk.
+    fn from_json(kind: BatchKind, json: &[u8]) -> Option<Self> {
+        match kind {
+            BatchKind::Events => {
+                let envelope: EventEnvelope = serde_json::from_slice(json).ok()?;
+                Some(Batch::Events(envelope.events))
+            }
+            #[cfg(feature = "tracing")]
+            BatchKind::Traces => {
+                let envelope: TraceEnvelope = serde_json::from_slice(json).ok()?;
+                Some(Batch::Traces(envelope.traces))
+            }
+            #[cfg(not(feature = "tracing"))]
+            BatchKind::Traces => None,
+        }
+    }
+}
+
+/// Delivery state shared by the client and its spawned uploads.
+#[derive(Clone)]
+struct Uploader {
+    transport: Arc<dyn Transport>,
+    retry: RetryPolicy,
+    /// Deadline set by a rate-limited send; no uploads are sent before it.
+    paused_until: Arc<Mutex<Option<Instant>>>,
+    /// Limits how many uploads run at once.
+    slots: Arc<Semaphore>,
+    spool: Option<Arc<Spool>>,
+}
+
+impl Uploader {
+    fn is_paused(&self) -> bool {
+        self.paused_until
+            .lock()
+            .unwrap()
+            .is_some_and(|until| until > Instant::now())
+    }
+
+    /// Pause all uploads for `duration`, clamped to the retry policy's
+    /// `max_retry_after`, never shortening an existing pause.
+    fn pause_for(&self, duration: Duration) {
+        let until = Instant::now() + self.retry.retry_after(duration);
+        let mut paused = self.paused_until.lock().unwrap();
+        if paused.is_none_or(|current| current < until) {
+            *paused = Some(until);
+        }
+    }
+
+    /// Time left until a pause ends, if uploads are paused.
+    fn remaining_pause(&self) -> Option<Duration> {
+        let until = (*self.paused_until.lock().unwrap())?;
+        Some(until.saturating_duration_since(Instant::now())).filter(|left| !left.is_zero())
+    }
+
+    /// Send a batch, keeping it in the spool until it is delivered.
+    ///
+    /// A rate-limited batch comes back as `Err(RateLimited)` and is not kept
+    /// in the spool: the caller puts it back in its buffer instead. After a
+    /// transient failure the spooled file is queued for the next replay.
+    async fn send(&self, batch: &Batch) -> Result<(), BloopError> {
+        let Some(spool) = &self.spool else {
+            return self.deliver(batch).await;
+        };
+
+        let spooled = match batch.to_json() {
+            Ok(json) => spool.write(batch.kind(), json).await,
+            Err(_) => None,
+        };
+        let result = self.deliver(batch).await;
+        if let Some(path) = spooled {
+            match &result {
+                Ok(()) | Err(BloopError::RateLimited { .. }) => spool.remove(path).await,
+                Err(error) if error.is_transient() => spool.leftovers().await.push(path),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    /// Send a batch and record the result. The batch counts as pending
+    /// until the send completes, so an abandoned send is reported as such.
+    /// Returns true if the batch was rate limited and should be buffered
+    /// again; it is then left to the caller's pending count.
+    async fn send_reported(&self, batch: &Batch, report: &mut FlushReport) -> bool {
+        let (kind, len) = (batch.kind(), batch.len());
+        report.pending += len;
+        let result = self.send(batch).await;
+        report.pending -= len;
+        if let Err(BloopError::RateLimited { .. }) = result {
+            return true;
+        }
+        report.record(kind, len, result);
+        false
+    }
+
+    /// Deliver spooled batches, left by a previous process or by a failed
+    /// upload, removing each one the transport accepts. Stops at the first
+    /// batch that fails transiently, keeping it for the next replay.
+    async fn replay_spool(&self, report: &mut FlushReport) {
+        let Some(spool) = &self.spool else { return };
+        let mut leftovers = spool.leftovers().await;
+        while let Some(path) = leftovers.first().cloned() {
+            let Ok((kind, json)) = spool.read(&path).await else {
+                leftovers.remove(0);
+                continue;
+            };
+            let Some(batch) = Batch::from_json(kind, &json) else {
+                leftovers.remove(0);
+                continue;
+            };
+            let (kind, len) = (batch.kind(), batch.len());
+            report.pending += len;
+            let result = self.deliver(&batch).await;
+            match result {
+                // Still on disk; replay it once the pause is over.
+                Err(BloopError::RateLimited { .. }) => break,
+                Err(error) if error.is_transient() => {
+                    report.pending -= len;
+                    report.record(kind, len, Err(error));
+                    break;
+                }
+                result => {
+                    report.pending -= len;
+                    leftovers.remove(0);
+                    if result.is_ok() {
+                        spool.remove(path).await;
+                    }
+                    report.record(kind, len, result);
+                }
+            }
+        }
+    }
+
+    /// Hand a batch to the transport, retrying transient failures and
+    /// pausing all uploads when rate limited. Returns the last error if the
+    /// batch was not delivered, and `RateLimited` without waiting when a
+    /// pause outlasts the retry policy's `max_delay`.
+    async fn deliver(&self, batch: &Batch) -> Result<(), BloopError> {
+        let _slot = self
+            .slots
+            .acquire()
+            .await
+            .expect("upload slots are never closed");
+        let mut attempt = 1;
+
+        loop {
+            if let Some(remaining) = self.remaining_pause() {
+                if remaining > self.retry.longest_delay() {
+                    return Err(BloopError::RateLimited { retry_after: Some(remaining) });
+                }
+                tokio::time::sleep(remaining).await;
+            }
+            let outcome = match batch {
+                Batch::Events(events) => self.transport.send_errors(events).await,
+                #[cfg(feature = "tracing")]
+                Batch::Traces(traces) => self.transport.send_traces(traces).await,
+            };
+
+            let error = match outcome {
+                SendOutcome::Delivered => return Ok(()),
+                SendOutcome::Rejected(error) => return Err(error),
+                SendOutcome::RateLimited { retry_after } => {
+                    self.pause_for(retry_after.unwrap_or_else(|| self.retry.backoff(attempt)));
+                    BloopError::RateLimited { retry_after }
+                }
+                SendOutcome::Failed(error) => error,
+            };
+
+            if attempt >= self.retry.attempts() {
+                return Err(error);
+            }
+            tokio::time::sleep(self.retry.delay(attempt)).await;
+            attempt += 1;
+        }
+    }
 }
@@ -0,0 +1,42 @@
+/// Compression applied to batch request bodies.
+///
+/// The request signature is computed over the compressed bytes, and the
+/// matching `Content-Encoding` header is sent with each request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Value of the `Content-Encoding` header, if any.
+    pub(crate) fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Some("gzip"),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    pub(crate) fn encode(self, body: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(body),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&body)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(body.as_slice(), 0),
+        }
+    }
+}
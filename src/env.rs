@@ -0,0 +1,89 @@
+use std::str::FromStr;
+use crate::client::BloopClientBuilder;
+use crate::error::BloopError;
+
+impl BloopClientBuilder {
+    /// Start a builder from `BLOOP_*` environment variables.
+    ///
+    /// | Variable                   | Builder setting      |
+    /// |----------------------------|----------------------|
+    /// | `BLOOP_ENDPOINT`           | `endpoint`           |
+    /// | `BLOOP_PROJECT_KEY`        | `project_key`        |
+    /// | `BLOOP_ENVIRONMENT`        | `environment`        |
+    /// | `BLOOP_RELEASE`            | `release`            |
+    /// | `BLOOP_SOURCE`             | `source`             |
+    /// | `BLOOP_MAX_BUFFER_SIZE`    | `max_buffer_size`    |
+    /// | `BLOOP_SAMPLE_RATE`        | `sample_rate`        |
+    /// | `BLOOP_TRACES_SAMPLE_RATE` | `traces_sample_rate` |
+    /// | `BLOOP_DISABLED`           | `enabled` (inverted) |
+    ///
+    /// Unset or empty variables keep the defaults. Setters called on the
+    /// returned builder override values read from the environment.
+    pub fn from_env() -> Result<Self, BloopError> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`from_env`](Self::from_env), but reads each `BLOOP_*` setting
+    /// through `lookup` instead of the process environment.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, BloopError> {
+        let var = |name: &str| lookup(name).filter(|v| !v.trim().is_empty());
+        let mut builder = Self::new();
+
+        if let Some(endpoint) = var("BLOOP_ENDPOINT") {
+            builder = builder.endpoint(endpoint);
+        }
+        if let Some(key) = var("BLOOP_PROJECT_KEY") {
+            builder = builder.project_key(key);
+        }
+        if let Some(env) = var("BLOOP_ENVIRONMENT") {
+            builder = builder.environment(env);
+        }
+        if let Some(release) = var("BLOOP_RELEASE") {
+            builder = builder.release(release);
+        }
+        if let Some(source) = var("BLOOP_SOURCE") {
+            builder = builder.source(source);
+        }
+        if let Some(size) = var("BLOOP_MAX_BUFFER_SIZE") {
+            builder = builder.max_buffer_size(parse("BLOOP_MAX_BUFFER_SIZE", &size)?);
+        }
+        if let Some(rate) = var("BLOOP_SAMPLE_RATE") {
+            builder = builder.sample_rate(parse_rate("BLOOP_SAMPLE_RATE", &rate)?);
+        }
+        if let Some(rate) = var("BLOOP_TRACES_SAMPLE_RATE") {
+            builder = builder.traces_sample_rate(parse_rate("BLOOP_TRACES_SAMPLE_RATE", &rate)?);
+        }
+        if let Some(disabled) = var("BLOOP_DISABLED") {
+            builder = builder.enabled(!parse_bool("BLOOP_DISABLED", &disabled)?);
+        }
+
+        Ok(builder)
+    }
+}
+
+fn invalid(name: &'static str, value: &str, expected: &str) -> BloopError {
+    BloopError::InvalidConfig {
+        setting: name,
+        reason: format!("{value:?} is not {expected}"),
+    }
+}
+
+fn parse<T: FromStr>(name: &'static str, value: &str) -> Result<T, BloopError> {
+    value.trim().parse().map_err(|_| invalid(name, value, "a number"))
+}
+
+fn parse_rate(name: &'static str, value: &str) -> Result<f64, BloopError> {
+    let rate: f64 = parse(name, value)?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(invalid(name, value, "between 0 and 1"));
+    }
+    Ok(rate)
+}
+
+fn parse_bool(name: &'static str, value: &str) -> Result<bool, BloopError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(invalid(name, value, "a boolean")),
+    }
+}
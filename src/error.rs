@@ -0,0 +1,56 @@
+use std::fmt;
+use std::time::Duration;
+use crate::retry::RetryPolicy;
+
+/// Errors reported by the client builder and by batch delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BloopError {
+    /// A required builder setting was not provided.
+    MissingConfig(&'static str),
+    /// A builder setting has an unusable value.
+    InvalidConfig { setting: &'static str, reason: String },
+    /// The endpoint is not a valid http(s) URL.
+    InvalidEndpoint { url: String, reason: String },
+    /// A batch could not be serialized or compressed.
+    Serialization(String),
+    /// The server rejected the project key (HTTP 401 or 403).
+    Unauthorized { status: u16 },
+    /// The server answered with another non-success status.
+    Http { status: u16 },
+    /// The server asked us to slow down.
+    RateLimited { retry_after: Option<Duration> },
+    /// The request never got a response: DNS, connect, TLS, timeout, ...
+    Network(String),
+}
+
+impl BloopError {
+    /// Whether a delivery failure may succeed if the batch is sent again
+    /// later: network errors, rate limits and retryable HTTP statuses.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            BloopError::Network(_) | BloopError::RateLimited { .. } => true,
+            BloopError::Http { status } => RetryPolicy::is_retryable_status(*status),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for BloopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BloopError::MissingConfig(setting) => write!(f, "{setting} is required"),
+            BloopError::InvalidConfig { setting, reason } => write!(f, "invalid {setting}: {reason}"),
+            BloopError::InvalidEndpoint { url, reason } => write!(f, "invalid endpoint {url:?}: {reason}"),
+            BloopError::Serialization(reason) => write!(f, "failed to serialize batch: {reason}"),
+            BloopError::Unauthorized { status } => write!(f, "project key rejected (HTTP {status})"),
+            BloopError::Http { status } => write!(f, "ingest endpoint returned HTTP {status}"),
+            BloopError::RateLimited { retry_after: Some(delay) } => {
+                write!(f, "rate limited, retry after {}s", delay.as_secs())
+            }
+            BloopError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            BloopError::Network(reason) => write!(f, "network error: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BloopError {}
@@ -1,4 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use crate::breadcrumb::Breadcrumb;
+use crate::client::BloopClient;
+use crate::level::Level;
+use crate::scrub::Redactions;
+use crate::stack::StackFrame;
 
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct Event {
@@ -20,23 +25,35 @@ pub struct Event {
     pub user_id_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    /// Groups repeats of the same error. Derived from the error type, route
+    /// and top stack frames when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Severity; [`Level::Error`] when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<Level>,
 }
 
-/// Internal ingest event with timestamp + environment fields.
-#[derive(Debug, Serialize)]
-pub(crate) struct IngestEvent {
+/// An event as sent to the ingest endpoint, with timestamp + environment fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestEvent {
     pub timestamp: i64,
     pub source: String,
     pub environment: String,
     pub release: String,
     pub error_type: String,
     pub message: String,
+    #[serde(default = "error_level")]
+    pub level: Level,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub route_or_procedure: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub screen: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stack: Option<String>,
+    /// `stack` parsed into frames, when it is a Rust backtrace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<StackFrame>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub http_status: Option<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -45,4 +62,146 @@ pub(crate) struct IngestEvent {
     pub user_id_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub fingerprint: String,
+    /// How many times this event occurred within the dedup window.
+    #[serde(default = "one")]
+    pub occurrences: u64,
+    /// Redactions made by the scrubber, by rule name.
+    #[serde(default, skip_serializing_if = "Redactions::is_empty")]
+    pub redactions: Redactions,
+    /// Breadcrumbs from the scope at capture time, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+fn one() -> u64 {
+    1
+}
+
+fn error_level() -> Level {
+    Level::Error
+}
+
+/// Number of top frames that contribute to a derived fingerprint.
+const FINGERPRINT_FRAMES: usize = 5;
+
+/// Fingerprint of an error type, route and the top frames of its stack:
+/// in-app frames when there are any, otherwise all of them. Only function
+/// names are used so the fingerprint survives unrelated line changes.
+pub(crate) fn fingerprint(error_type: &str, route: Option<&str>, frames: &[StackFrame]) -> String {
+    let in_app = frames.iter().any(|frame| frame.in_app);
+    let top = frames
+        .iter()
+        .filter(|frame| frame.in_app || !in_app)
+        .take(FINGERPRINT_FRAMES)
+        .map(|frame| frame.function.as_str());
+    hash_parts([error_type, route.unwrap_or_default()].into_iter().chain(top))
+}
+
+/// SHA-256 over NUL-separated parts, hex encoded.
+pub(crate) fn hash_parts<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        if i > 0 {
+            hasher.update([0]);
+        }
+        hasher.update(part);
+    }
+    hex::encode(hasher.finalize())
+}
+
+impl Event {
+    /// Set `user_id_hash` from a raw user id, hashed with
+    /// [`BloopClient::hash_user_id`]. The raw id is not kept.
+    pub fn hashed_user_id(mut self, client: &BloopClient, user_id: &str) -> Self {
+        self.user_id_hash = Some(client.hash_user_id(user_id));
+        self
+    }
+
+    /// Build an event from an error value.
+    ///
+    /// `error_type` is the concrete type name (`"Error"` for a `dyn Error`,
+    /// whose type cannot be recovered), `message` its `Display` output, and
+    /// every error in the `source()` chain is recorded under
+    /// `metadata.causes` as `{"type", "message"}`, outermost first. When
+    /// backtraces are enabled (`RUST_BACKTRACE`), one captured here is
+    /// attached as `stack`.
+    pub fn from_error<E: std::error::Error + ?Sized>(error: &E) -> Self {
+        let type_name = std::any::type_name::<E>();
+        let error_type = if type_name.starts_with("dyn ") {
+            UNKNOWN_ERROR_TYPE
+        } else {
+            type_name
+        };
+
+        let causes = std::iter::successors(error.source(), |cause| cause.source());
+
+        Event {
+            error_type: error_type.to_string(),
+            message: error.to_string(),
+            stack: captured(&std::backtrace::Backtrace::capture()),
+            metadata: causes_metadata(causes),
+            ..Default::default()
+        }
+    }
+}
+
+/// `{"causes": [{"type", "message"}, ...]}`, or `None` for an empty chain.
+pub(crate) fn causes_metadata<'a>(
+    causes: impl Iterator<Item = &'a (dyn std::error::Error + 'static)>,
+) -> Option<serde_json::Value> {
+    let causes: Vec<_> = causes
+        .map(|cause| {
+            serde_json::json!({
+                "type": erased_type_name(cause),
+                "message": cause.to_string(),
+            })
+        })
+        .collect();
+    (!causes.is_empty()).then(|| serde_json::json!({ "causes": causes }))
+}
+
+/// The backtrace text, if one was actually captured.
+pub(crate) fn captured(backtrace: &std::backtrace::Backtrace) -> Option<String> {
+    match backtrace.status() {
+        std::backtrace::BacktraceStatus::Captured => Some(backtrace.to_string()),
+        _ => None,
+    }
+}
+
+/// `error_type` recorded for an erased error whose type is not known.
+const UNKNOWN_ERROR_TYPE: &str = "Error";
+
+/// Type name of an erased error, for the standard library errors that can
+/// be recognized by downcasting; `"Error"` for anything else.
+pub(crate) fn erased_type_name(error: &(dyn std::error::Error + 'static)) -> &'static str {
+    macro_rules! known {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if error.is::<$ty>() {
+                    return std::any::type_name::<$ty>();
+                }
+            )*
+        };
+    }
+    known!(
+        std::io::Error,
+        std::fmt::Error,
+        std::num::ParseIntError,
+        std::num::ParseFloatError,
+        std::num::TryFromIntError,
+        std::str::ParseBoolError,
+        std::char::ParseCharError,
+        std::str::Utf8Error,
+        std::string::FromUtf8Error,
+        std::string::FromUtf16Error,
+        std::net::AddrParseError,
+        std::env::VarError,
+        std::time::SystemTimeError,
+        std::array::TryFromSliceError,
+    );
+    UNKNOWN_ERROR_TYPE
 }
@@ -0,0 +1,75 @@
+//! Conversions from `anyhow` and `eyre` error reports into [`Event`]s.
+//!
+//! Both keep what stringifying an error loses: `message` is the outermost
+//! context, `error_type` the root cause's type, each layer below the top is
+//! recorded under `metadata.causes`, and the report's own backtrace (when
+//! one was captured) becomes `stack`.
+//!
+//! The root cause is type-erased, so its type is only known for standard
+//! library errors (`std::io::Error`, `ParseIntError`, ...); anything else,
+//! including `anyhow!`/`eyre!` messages and custom error types, is recorded
+//! as `"Error"`. Such events share an `error_type`, so they only group apart
+//! by route and stack; set `error_type` or `fingerprint` on the converted
+//! event to group them yourself.
+
+use crate::event::{causes_metadata, erased_type_name, Event};
+
+#[cfg(feature = "anyhow")]
+use crate::event::captured;
+
+/// `message` is the outermost context, `metadata.causes` the chain below it
+/// and `stack` the report's backtrace, if captured. `error_type` is the root
+/// cause's type only for standard library errors; `anyhow!` messages and
+/// custom error types are all recorded as `"Error"` and group together
+/// unless you set `error_type` or `fingerprint` on the event.
+#[cfg(feature = "anyhow")]
+impl From<&anyhow::Error> for Event {
+    fn from(error: &anyhow::Error) -> Self {
+        Event {
+            error_type: erased_type_name(error.root_cause()).to_string(),
+            message: error.to_string(),
+            stack: captured(error.backtrace()),
+            metadata: causes_metadata(error.chain().skip(1)),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for Event {
+    fn from(error: anyhow::Error) -> Self {
+        Event::from(&error)
+    }
+}
+
+/// `message` is the outermost context, `metadata.causes` the chain below it
+/// and `stack` the report's backtrace, if captured. `error_type` is the root
+/// cause's type only for standard library errors; `eyre!` messages and
+/// custom error types are all recorded as `"Error"` and group together
+/// unless you set `error_type` or `fingerprint` on the event.
+#[cfg(feature = "eyre")]
+impl From<&eyre::Report> for Event {
+    fn from(report: &eyre::Report) -> Self {
+        // eyre only exposes the backtrace through the default handler's
+        // `Debug` output, after the chain.
+        let debug = format!("{report:?}");
+        let stack = debug
+            .split_once("\n\nStack backtrace:\n")
+            .map(|(_, backtrace)| backtrace.to_string());
+
+        Event {
+            error_type: erased_type_name(report.root_cause()).to_string(),
+            message: report.to_string(),
+            stack,
+            metadata: causes_metadata(report.chain().skip(1)),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl From<eyre::Report> for Event {
+    fn from(report: eyre::Report) -> Self {
+        Event::from(&report)
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Severity of an event or breadcrumb.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+    Fatal,
+}
@@ -1,15 +1,52 @@
 mod client;
 mod event;
 mod signing;
+mod breadcrumb;
 mod buffer;
+mod compression;
+mod env;
+mod error;
+mod level;
+mod panic;
+mod rate_limit;
+mod report;
+mod retry;
+mod sampling;
+mod scope;
+mod scrub;
+mod spool;
+mod stack;
+mod transport;
 
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+mod integrations;
+#[cfg(feature = "test-utils")]
+mod testing;
 #[cfg(feature = "tracing")]
 mod tracing;
 #[cfg(feature = "tracing")]
 mod tracing_types;
 
-pub use client::{BloopClient, BloopClientBuilder};
-pub use event::Event;
+pub use breadcrumb::Breadcrumb;
+pub use buffer::OverflowPolicy;
+pub use client::{BloopClient, BloopClientBuilder, MESSAGE_ERROR_TYPE};
+pub use compression::Compression;
+pub use error::BloopError;
+pub use event::{Event, IngestEvent};
+pub use level::Level;
+pub use panic::PANIC_ERROR_TYPE;
+pub use rate_limit::SUPPRESSED_ERROR_TYPE;
+pub use report::{DeliveryFailure, FlushReport};
+pub use retry::RetryPolicy;
+pub use sampling::SamplingContext;
+pub use scope::Scope;
+pub use scrub::{Redactions, Scrubber};
+pub use spool::BatchKind;
+pub use stack::StackFrame;
+pub use transport::{HttpTransport, SendOutcome, Transport};
+
+#[cfg(feature = "test-utils")]
+pub use testing::RecordingTransport;
 
 #[cfg(feature = "tracing")]
 pub use tracing::{Trace, Span};
@@ -0,0 +1,94 @@
+use std::cell::Cell;
+use std::panic::PanicHookInfo;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::client::BloopClient;
+use crate::event::Event;
+use crate::level::Level;
+
+/// Error type given to events reported by the panic hook.
+pub const PANIC_ERROR_TYPE: &str = "Panic";
+
+/// How long the panic hook waits for buffered events to upload.
+const PANIC_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+thread_local! {
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+impl BloopClient {
+    /// Report panics as events.
+    ///
+    /// The installed hook runs the previously installed hook first (so the
+    /// usual message still reaches stderr), then captures the panic with its
+    /// payload, location and a backtrace, and flushes synchronously, waiting
+    /// up to five seconds, so the event is sent even if the process aborts.
+    pub fn install_panic_hook(self: &Arc<Self>) {
+        let client = Arc::downgrade(self);
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+
+            // A panic inside our own reporting must not recurse.
+            if IN_HOOK.with(|flag| flag.replace(true)) {
+                return;
+            }
+            if let Some(client) = client.upgrade() {
+                client.capture_buffered(panic_event(info));
+                flush_blocking(&client);
+            }
+            IN_HOOK.with(|flag| flag.set(false));
+        }));
+    }
+}
+
+fn panic_event(info: &PanicHookInfo<'_>) -> Event {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+
+    let mut metadata = serde_json::Map::new();
+    if let Some(location) = info.location() {
+        metadata.insert(
+            "location".into(),
+            serde_json::json!({
+                "file": location.file(),
+                "line": location.line(),
+                "column": location.column(),
+            }),
+        );
+    }
+    if let Some(name) = std::thread::current().name() {
+        metadata.insert("thread".into(), name.into());
+    }
+
+    Event {
+        error_type: PANIC_ERROR_TYPE.into(),
+        message,
+        level: Some(Level::Fatal),
+        stack: Some(std::backtrace::Backtrace::force_capture().to_string()),
+        metadata: Some(metadata.into()),
+        ..Default::default()
+    }
+}
+
+/// Flush on a private runtime in a scoped thread. This works whether or not
+/// the panicking thread is itself driving a tokio runtime.
+fn flush_blocking(client: &BloopClient) {
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            else {
+                return;
+            };
+            runtime.block_on(async {
+                let _ = tokio::time::timeout(PANIC_FLUSH_TIMEOUT, client.flush()).await;
+            });
+        });
+    });
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Error type of the summary events reporting rate-limited captures.
+pub const SUPPRESSED_ERROR_TYPE: &str = "EventsSuppressed";
+
+/// Token buckets keyed by fingerprint, so one noisy error cannot crowd out
+/// the rest. Each bucket holds up to `burst` tokens and refills at `burst`
+/// per `period`.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    burst: f64,
+    period: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    buckets: HashMap<String, Bucket>,
+    last_summary: Instant,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    error_type: String,
+    suppressed: u64,
+}
+
+/// Captures dropped for one fingerprint since the last summary.
+#[derive(Debug)]
+pub(crate) struct Suppressed {
+    pub fingerprint: String,
+    pub error_type: String,
+    pub count: u64,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, period: Duration) -> Self {
+        Self {
+            burst: burst.max(1) as f64,
+            period,
+            state: Mutex::new(State {
+                buckets: HashMap::new(),
+                last_summary: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token for `fingerprint`, counting the capture as suppressed
+    /// when there is none left.
+    pub fn allow(&self, fingerprint: &str, error_type: &str) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let bucket = state
+            .buckets
+            .entry(fingerprint.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: self.burst,
+                updated: now,
+                error_type: error_type.to_string(),
+                suppressed: 0,
+            });
+        self.refill(bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            bucket.suppressed += 1;
+            false
+        }
+    }
+
+    /// Suppressed counts since the last summary, once per period or
+    /// immediately with `force`. Resets the counts and forgets buckets that
+    /// have refilled completely.
+    pub fn take_suppressed(&self, force: bool) -> Vec<Suppressed> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if !force && now.duration_since(state.last_summary) < self.period {
+            return Vec::new();
+        }
+        state.last_summary = now;
+
+        let mut suppressed = Vec::new();
+        state.buckets.retain(|fingerprint, bucket| {
+            if bucket.suppressed > 0 {
+                suppressed.push(Suppressed {
+                    fingerprint: fingerprint.clone(),
+                    error_type: bucket.error_type.clone(),
+                    count: std::mem::take(&mut bucket.suppressed),
+                });
+            }
+            self.refill(bucket, now);
+            bucket.tokens < self.burst
+        });
+        suppressed
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        let rate = self.burst / self.period.as_secs_f64().max(f64::MIN_POSITIVE);
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst);
+        bucket.updated = now;
+    }
+}
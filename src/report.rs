@@ -0,0 +1,43 @@
+use crate::error::BloopError;
+use crate::spool::BatchKind;
+
+/// What a `flush()` or `shutdown()` call managed to deliver.
+#[derive(Debug, Clone, Default)]
+pub struct FlushReport {
+    /// Events accepted by the transport, including replayed spool files.
+    pub events_sent: usize,
+    /// Traces accepted by the transport, including replayed spool files.
+    pub traces_sent: usize,
+    /// Batches that could not be delivered.
+    pub failures: Vec<DeliveryFailure>,
+    /// Items still buffered or being uploaded when the call returned,
+    /// including any abandoned by a timed-out shutdown.
+    pub pending: usize,
+    /// Whether a `shutdown_timeout` deadline cut the shutdown short.
+    pub timed_out: bool,
+}
+
+/// A batch that was given up on.
+#[derive(Debug, Clone)]
+pub struct DeliveryFailure {
+    pub kind: BatchKind,
+    /// Number of events or traces in the batch.
+    pub items: usize,
+    /// Why the last attempt failed.
+    pub error: BloopError,
+}
+
+impl FlushReport {
+    /// True when everything was delivered and nothing is left pending.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty() && self.pending == 0 && !self.timed_out
+    }
+
+    pub(crate) fn record(&mut self, kind: BatchKind, items: usize, result: Result<(), BloopError>) {
+        match (result, kind) {
+            (Ok(()), BatchKind::Events) => self.events_sent += items,
+            (Ok(()), BatchKind::Traces) => self.traces_sent += items,
+            (Err(error), kind) => self.failures.push(DeliveryFailure { kind, items, error }),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+/// Retry policy for failed batch uploads.
+///
+/// Network errors and retryable status codes (408, 429 and 5xx) are retried
+/// with exponential backoff. Other 4xx responses are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retry_after: Duration,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            max_retry_after: Duration::from_secs(600),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that sends each batch exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Total number of attempts, including the first one. Clamped to at least 1.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Longest pause honored from a server's `Retry-After`; longer requests
+    /// are clamped to it. Defaults to 10 minutes.
+    pub fn max_retry_after(mut self, delay: Duration) -> Self {
+        self.max_retry_after = delay;
+        self
+    }
+
+    /// Fraction of each delay (0.0 to 1.0) that is randomized.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// A server-requested pause, clamped to `max_retry_after`.
+    pub fn retry_after(&self, requested: Duration) -> Duration {
+        requested.min(self.max_retry_after)
+    }
+
+    /// The longest wait between attempts; longer rate-limit pauses are not
+    /// waited out by a single upload.
+    pub(crate) fn longest_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Backoff before retry number `retry` (1-based), without jitter applied.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Backoff before retry number `retry` (1-based), with jitter applied.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        backoff.mul_f64(1.0 - self.jitter * random_unit())
+    }
+
+    /// Whether a response with this HTTP status should be retried.
+    pub fn is_retryable_status(status: u16) -> bool {
+        matches!(status, 408 | 429 | 500..=599)
+    }
+}
+
+/// Uniform random value in `[0, 1)`, drawn from the v4 UUID generator.
+pub(crate) fn random_unit() -> f64 {
+    // The low 56 bits of a v4 UUID are free of version/variant bits.
+    let bits = uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1);
+    bits as f64 / (1u64 << 53) as f64
+}
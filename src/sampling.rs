@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::event::Event;
+use crate::retry::random_unit;
+
+/// What a custom sampler is asked about.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SamplingContext<'a> {
+    /// An error passed to `capture`.
+    Error(&'a Event),
+    /// A trace being started.
+    Trace { name: &'a str },
+}
+
+pub(crate) type SamplerFn = Arc<dyn Fn(&SamplingContext<'_>) -> Option<f64> + Send + Sync>;
+
+/// Sample rates for errors and traces. The custom sampler is consulted
+/// first; when it returns `None`, an override for the error type or trace
+/// name applies, and otherwise the global rate.
+#[derive(Clone)]
+pub(crate) struct Sampling {
+    pub errors: f64,
+    pub traces: f64,
+    pub error_types: HashMap<String, f64>,
+    pub trace_names: HashMap<String, f64>,
+    pub sampler: Option<SamplerFn>,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            errors: 1.0,
+            traces: 1.0,
+            error_types: HashMap::new(),
+            trace_names: HashMap::new(),
+            sampler: None,
+        }
+    }
+}
+
+impl Sampling {
+    pub fn error_rate(&self, event: &Event) -> f64 {
+        self.custom(&SamplingContext::Error(event))
+            .or_else(|| self.error_types.get(&event.error_type).copied())
+            .unwrap_or(self.errors)
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    pub fn trace_rate(&self, name: &str) -> f64 {
+        self.custom(&SamplingContext::Trace { name })
+            .or_else(|| self.trace_names.get(name).copied())
+            .unwrap_or(self.traces)
+    }
+
+    fn custom(&self, context: &SamplingContext<'_>) -> Option<f64> {
+        let sampler = self.sampler.as_ref()?;
+        sampler(context).map(|rate| rate.clamp(0.0, 1.0))
+    }
+}
+
+/// Keep an item with probability `rate`.
+pub(crate) fn sampled(rate: f64) -> bool {
+    rate >= 1.0 || random_unit() < rate
+}
@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::{PoisonError, TryLockError};
+use serde_json::{Map, Value};
+use crate::breadcrumb::Breadcrumb;
+use crate::client::BloopClient;
+use crate::event::IngestEvent;
+
+thread_local! {
+    static THREAD_SCOPES: RefCell<Vec<Scope>> = const { RefCell::new(Vec::new()) };
+}
+
+tokio::task_local! {
+    static TASK_SCOPE: RefCell<Scope>;
+}
+
+/// Context merged into every event captured while it is active.
+///
+/// Scopes layer: the client's global scope, then the current tokio task's
+/// scope, then the innermost [`BloopClient::with_scope`] on this thread.
+/// Inner layers override outer ones, and anything set on the event itself
+/// wins over all of them. Tags are merged into `metadata.tags` and extras
+/// into the top level of `metadata` (when it is a JSON object). Breadcrumbs
+/// from all layers are combined in time order.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    tags: BTreeMap<String, String>,
+    extra: Map<String, Value>,
+    user_id_hash: Option<String>,
+    request_id: Option<String>,
+    route_or_procedure: Option<String>,
+    release: Option<String>,
+    breadcrumbs: VecDeque<Breadcrumb>,
+}
+
+impl Scope {
+    pub fn set_tag(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.tags.insert(key.into(), value.into());
+    }
+
+    pub fn set_extra(&mut self, key: impl Into<String>, value: impl Into<Value>) {
+        self.extra.insert(key.into(), value.into());
+    }
+
+    /// Set the user, as a hash from [`BloopClient::hash_user_id`].
+    pub fn set_user_id_hash(&mut self, hash: impl Into<String>) {
+        self.user_id_hash = Some(hash.into());
+    }
+
+    pub fn set_request_id(&mut self, id: impl Into<String>) {
+        self.request_id = Some(id.into());
+    }
+
+    pub fn set_route_or_procedure(&mut self, route: impl Into<String>) {
+        self.route_or_procedure = Some(route.into());
+    }
+
+    /// Override the client's release for events captured in this scope.
+    pub fn set_release(&mut self, release: impl Into<String>) {
+        self.release = Some(release.into());
+    }
+
+    /// Record a breadcrumb, dropping the oldest beyond `max`.
+    fn add_breadcrumb(&mut self, breadcrumb: Breadcrumb, max: usize) {
+        self.breadcrumbs.push_back(breadcrumb);
+        while self.breadcrumbs.len() > max {
+            self.breadcrumbs.pop_front();
+        }
+    }
+
+    /// Layer `inner` over this scope.
+    fn push(&mut self, inner: &Scope) {
+        self.tags.extend(inner.tags.clone());
+        self.extra.extend(inner.extra.clone());
+        self.breadcrumbs.extend(inner.breadcrumbs.iter().cloned());
+        for (outer, inner) in [
+            (&mut self.user_id_hash, &inner.user_id_hash),
+            (&mut self.request_id, &inner.request_id),
+            (&mut self.route_or_procedure, &inner.route_or_procedure),
+            (&mut self.release, &inner.release),
+        ] {
+            if inner.is_some() {
+                outer.clone_from(inner);
+            }
+        }
+    }
+
+    /// Fill in whatever the event does not set itself.
+    pub(crate) fn apply(&self, event: &mut IngestEvent) {
+        if let Some(release) = &self.release {
+            event.release.clone_from(release);
+        }
+        for (field, value) in [
+            (&mut event.user_id_hash, &self.user_id_hash),
+            (&mut event.request_id, &self.request_id),
+            (&mut event.route_or_procedure, &self.route_or_procedure),
+        ] {
+            if field.is_none() {
+                field.clone_from(value);
+            }
+        }
+        if event.breadcrumbs.is_empty() {
+            event.breadcrumbs = self.breadcrumbs.iter().cloned().collect();
+        }
+
+        if self.tags.is_empty() && self.extra.is_empty() {
+            return;
+        }
+        let metadata = event
+            .metadata
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(metadata) = metadata else {
+            return;
+        };
+        for (key, value) in &self.extra {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        if !self.tags.is_empty() {
+            let tags = metadata
+                .entry("tags")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(tags) = tags {
+                for (key, value) in &self.tags {
+                    tags.entry(key.clone()).or_insert_with(|| value.clone().into());
+                }
+            }
+        }
+    }
+}
+
+/// Pops this thread's innermost scope, even if the closure panics.
+struct PopOnDrop;
+
+impl Drop for PopOnDrop {
+    fn drop(&mut self) {
+        THREAD_SCOPES.with(|scopes| scopes.borrow_mut().pop());
+    }
+}
+
+impl BloopClient {
+    /// Edit the client's global scope, which applies to every event.
+    pub fn configure_scope(&self, configure: impl FnOnce(&mut Scope)) {
+        configure(&mut self.scope.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    /// Run `f` with a scope configured on top of this thread's current one.
+    ///
+    /// The scope is thread-local, so in async code it does not follow a
+    /// task across `.await` points; use [`with_task_scope`] there.
+    ///
+    /// [`with_task_scope`]: BloopClient::with_task_scope
+    pub fn with_scope<R>(&self, configure: impl FnOnce(&mut Scope), f: impl FnOnce() -> R) -> R {
+        let mut scope = THREAD_SCOPES
+            .with(|scopes| scopes.borrow().last().cloned())
+            .unwrap_or_default();
+        configure(&mut scope);
+        THREAD_SCOPES.with(|scopes| scopes.borrow_mut().push(scope));
+        let _pop = PopOnDrop;
+        f()
+    }
+
+    /// Run a future with a scope configured on top of the current task's
+    /// one. The scope follows the future across `.await` points and threads.
+    pub async fn with_task_scope<F: Future>(
+        &self,
+        configure: impl FnOnce(&mut Scope),
+        future: F,
+    ) -> F::Output {
+        let mut scope = TASK_SCOPE
+            .try_with(|scope| scope.borrow().clone())
+            .unwrap_or_default();
+        configure(&mut scope);
+        TASK_SCOPE.scope(RefCell::new(scope), future).await
+    }
+
+    /// Record a breadcrumb in the innermost active scope: this thread's
+    /// `with_scope`, else the task scope, else the global one. Each scope
+    /// keeps only the most recent `max_breadcrumbs`.
+    pub fn add_breadcrumb(&self, breadcrumb: Breadcrumb) {
+        let max = self.max_breadcrumbs;
+        if THREAD_SCOPES.with(|scopes| !scopes.borrow().is_empty()) {
+            THREAD_SCOPES.with(|scopes| {
+                if let Some(scope) = scopes.borrow_mut().last_mut() {
+                    scope.add_breadcrumb(breadcrumb, max);
+                }
+            });
+        } else if TASK_SCOPE.try_with(|_| ()).is_ok() {
+            TASK_SCOPE.with(|scope| scope.borrow_mut().add_breadcrumb(breadcrumb, max));
+        } else {
+            self.scope
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .add_breadcrumb(breadcrumb, max);
+        }
+    }
+
+    /// The global, task and thread scopes layered together.
+    pub(crate) fn current_scope(&self) -> Scope {
+        let mut scope = self.scope.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        let _ = TASK_SCOPE.try_with(|task| scope.push(&task.borrow()));
+        THREAD_SCOPES.with(|scopes| {
+            if let Some(thread) = scopes.borrow().last() {
+                scope.push(thread);
+            }
+        });
+        self.finish_scope(scope)
+    }
+
+    /// Like [`current_scope`](Self::current_scope), but never blocks or
+    /// panics: a layer that is locked or borrowed (e.g. when panicking inside
+    /// [`configure_scope`](Self::configure_scope)) is skipped.
+    pub(crate) fn try_current_scope(&self) -> Scope {
+        let mut scope = match self.scope.try_lock() {
+            Ok(global) => global.clone(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+            Err(TryLockError::WouldBlock) => Scope::default(),
+        };
+        let _ = TASK_SCOPE.try_with(|task| {
+            if let Ok(task) = task.try_borrow() {
+                scope.push(&task);
+            }
+        });
+        let _ = THREAD_SCOPES.try_with(|scopes| {
+            if let Some(thread) = scopes.try_borrow().ok().as_ref().and_then(|s| s.last()) {
+                scope.push(thread);
+            }
+        });
+        self.finish_scope(scope)
+    }
+
+    /// Order breadcrumbs from all layers by time, keeping the newest.
+    fn finish_scope(&self, mut scope: Scope) -> Scope {
+        scope
+            .breadcrumbs
+            .make_contiguous()
+            .sort_by_key(|breadcrumb| breadcrumb.timestamp);
+        while scope.breadcrumbs.len() > self.max_breadcrumbs {
+            scope.breadcrumbs.pop_front();
+        }
+        scope
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use regex::Regex;
+use serde_json::Value;
+use crate::error::BloopError;
+use crate::event::IngestEvent;
+
+#[cfg(feature = "tracing")]
+use crate::tracing::Trace;
+
+/// Redaction counts by rule name.
+pub type Redactions = BTreeMap<String, u64>;
+
+const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// Rule name counted when a metadata value is removed by key.
+const DENIED_KEY_RULE: &str = "denied_key";
+
+/// Metadata keys whose values are always redacted by [`Scrubber::new`].
+/// Keys match case-insensitively, with `-` and `_` treated alike.
+const DEFAULT_DENIED_KEYS: &[&str] = &[
+    "password", "passwd", "secret", "client_secret", "token", "access_token",
+    "refresh_token", "authorization", "cookie", "set_cookie", "api_key", "apikey",
+    "x_api_key",
+];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    pattern: Regex,
+    /// Extra check on a match, to cut false positives.
+    check: Option<fn(&str) -> bool>,
+}
+
+/// Redacts personal data and secrets from events and traces before they are
+/// buffered.
+///
+/// Text is matched against regex rules and every match is replaced with the
+/// marker (`[REDACTED]` by default). In JSON metadata, string values are
+/// scrubbed the same way and values under a denied key are replaced whole.
+/// How many redactions each rule made is recorded in the payload's
+/// `redactions` field.
+///
+/// Scrubbed: event `message`, `metadata` and breadcrumbs; trace `input`, `output` and
+/// `metadata`; span `input`, `output`, `error_message` and `metadata`.
+#[derive(Debug, Clone)]
+pub struct Scrubber {
+    rules: Vec<Rule>,
+    denied_keys: Vec<String>,
+    replacement: String,
+}
+
+impl Default for Scrubber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scrubber {
+    /// A scrubber with the built-in rules (`email`, `api_key`,
+    /// `credit_card`, `phone`) and the default key denylist.
+    pub fn new() -> Self {
+        let builtin = |name: &str, pattern: &str, check: Option<fn(&str) -> bool>| Rule {
+            name: name.to_string(),
+            pattern: Regex::new(pattern).expect("built-in scrub rule"),
+            check,
+        };
+        Self {
+            rules: vec![
+                builtin("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", None),
+                builtin(
+                    "api_key",
+                    concat!(
+                        r"\b(?:sk|pk|rk)[-_][A-Za-z0-9_-]{16,}",
+                        r"|\bAKIA[0-9A-Z]{16}\b",
+                        r"|\bgh[pousr]_[A-Za-z0-9]{36}\b",
+                        r"|\b[Bb]earer\s+[A-Za-z0-9._~+/-]+=*",
+                    ),
+                    None,
+                ),
+                builtin("credit_card", r"\b(?:\d[ -]?){12,18}\d\b", Some(luhn_valid)),
+                // Only formatted numbers: a leading `+`, an area code in
+                // parentheses, or separators. Bare digit runs are more often
+                // timestamps and ids.
+                builtin(
+                    "phone",
+                    concat!(
+                        r"\+\d{1,3}[\s.-]?(?:\(\d{1,4}\)|\d{1,4})(?:[\s.-]?\d{2,4}){2,3}\b",
+                        r"|\(\d{3}\)[\s.-]?\d{3}[\s.-]\d{4}\b",
+                        r"|\b\d{3}[\s.-]\d{3}[\s.-]\d{4}\b",
+                    ),
+                    None,
+                ),
+            ],
+            denied_keys: DEFAULT_DENIED_KEYS.iter().map(|k| k.to_string()).collect(),
+            replacement: DEFAULT_REPLACEMENT.into(),
+        }
+    }
+
+    /// A scrubber with no rules and an empty denylist.
+    pub fn empty() -> Self {
+        Self {
+            rules: Vec::new(),
+            denied_keys: Vec::new(),
+            replacement: DEFAULT_REPLACEMENT.into(),
+        }
+    }
+
+    /// Add a regex rule. Redactions it makes are counted under `name`.
+    pub fn rule(mut self, name: impl Into<String>, pattern: &str) -> Result<Self, BloopError> {
+        let pattern = Regex::new(pattern).map_err(|e| BloopError::InvalidConfig {
+            setting: "scrub rule",
+            reason: e.to_string(),
+        })?;
+        self.rules.push(Rule {
+            name: name.into(),
+            pattern,
+            check: None,
+        });
+        Ok(self)
+    }
+
+    /// Redact the whole value of any metadata entry with this key.
+    pub fn deny_key(mut self, key: impl AsRef<str>) -> Self {
+        self.denied_keys.push(normalize_key(key.as_ref()));
+        self
+    }
+
+    /// Text that replaces each redaction.
+    pub fn replacement(mut self, marker: impl Into<String>) -> Self {
+        self.replacement = marker.into();
+        self
+    }
+
+    /// Scrub a piece of text, counting redactions into `counts`.
+    pub fn scrub_text(&self, text: &str, counts: &mut Redactions) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let mut hits = 0;
+            let replaced = rule.pattern.replace_all(&text, |caps: &regex::Captures<'_>| {
+                let matched = &caps[0];
+                if rule.check.is_some_and(|check| !check(matched)) {
+                    return matched.to_string();
+                }
+                hits += 1;
+                self.replacement.clone()
+            });
+            if hits > 0 {
+                text = replaced.into_owned();
+                *counts.entry(rule.name.clone()).or_default() += hits;
+            }
+        }
+        text
+    }
+
+    /// Scrub every string in a JSON value and redact denied keys.
+    pub fn scrub_json(&self, value: &mut Value, counts: &mut Redactions) {
+        match value {
+            Value::String(text) => *text = self.scrub_text(text, counts),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.scrub_json(item, counts)),
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.denied_keys.contains(&normalize_key(key)) {
+                        *value = Value::String(self.replacement.clone());
+                        *counts.entry(DENIED_KEY_RULE.into()).or_default() += 1;
+                    } else {
+                        self.scrub_json(value, counts);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn scrub_event(&self, event: &mut IngestEvent) {
+        let mut counts = std::mem::take(&mut event.redactions);
+        event.message = self.scrub_text(&event.message, &mut counts);
+        self.scrub_opt_json(&mut event.metadata, &mut counts);
+        for breadcrumb in &mut event.breadcrumbs {
+            breadcrumb.message = self.scrub_text(&breadcrumb.message, &mut counts);
+            self.scrub_opt_json(&mut breadcrumb.data, &mut counts);
+        }
+        event.redactions = counts;
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn scrub_trace(&self, trace: &mut Trace) {
+        let mut counts = std::mem::take(&mut trace.redactions);
+        self.scrub_opt_text(&mut trace.input, &mut counts);
+        self.scrub_opt_text(&mut trace.output, &mut counts);
+        self.scrub_opt_json(&mut trace.metadata, &mut counts);
+        for span in &mut trace.spans {
+            self.scrub_opt_text(&mut span.input, &mut counts);
+            self.scrub_opt_text(&mut span.output, &mut counts);
+            self.scrub_opt_text(&mut span.error_message, &mut counts);
+            self.scrub_opt_json(&mut span.metadata, &mut counts);
+        }
+        trace.redactions = counts;
+    }
+
+    #[cfg(feature = "tracing")]
+    fn scrub_opt_text(&self, text: &mut Option<String>, counts: &mut Redactions) {
+        if let Some(text) = text {
+            *text = self.scrub_text(text, counts);
+        }
+    }
+
+    fn scrub_opt_json(&self, value: &mut Option<Value>, counts: &mut Redactions) {
+        if let Some(value) = value {
+            self.scrub_json(value, counts);
+        }
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.to_ascii_lowercase().replace('-', "_")
+}
+
+/// Luhn checksum over the digits of a candidate card number.
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
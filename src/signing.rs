@@ -3,9 +3,24 @@ use sha2::Sha256;
 
 type HmacSha256 = Hmac<Sha256>;
 
-pub fn sign(key: &str, body: &[u8]) -> String {
-    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
-        .expect("HMAC can take key of any size");
-    mac.update(body);
+/// Prefixed to user ids before hashing, so a user-id hash can never double
+/// as a request signature for the same bytes under the same key.
+const USER_ID_DOMAIN: &[u8] = b"bloop-user-id\0";
+
+fn hmac(key: &str, parts: &[&[u8]]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC can take key of any size");
+    for part in parts {
+        mac.update(part);
+    }
     hex::encode(mac.finalize().into_bytes())
 }
+
+pub fn sign(key: &str, body: &[u8]) -> String {
+    hmac(key, &[body])
+}
+
+/// Salted hash of a user identifier, so the raw id never leaves the process.
+pub(crate) fn hash_user_id(salt: &str, user_id: &str) -> String {
+    hmac(salt, &[USER_ID_DOMAIN, user_id.as_bytes()])
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Kind of batch: errors or traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchKind {
+    Events,
+    Traces,
+}
+
+impl BatchKind {
+    fn suffix(self) -> &'static str {
+        match self {
+            BatchKind::Events => ".events.json",
+            BatchKind::Traces => ".traces.json",
+        }
+    }
+
+    fn from_file_name(name: &str) -> Option<Self> {
+        [BatchKind::Events, BatchKind::Traces]
+            .into_iter()
+            .find(|kind| name.ends_with(kind.suffix()))
+    }
+}
+
+/// File-backed store for serialized batches that have not been delivered yet.
+///
+/// Each batch is written to its own file before it is sent and removed once
+/// the server accepts it. Files left behind by a previous process are picked
+/// up as leftovers when the spool is opened, and files whose upload failed
+/// transiently are added back to them. File I/O runs on tokio's blocking
+/// pool.
+#[derive(Debug)]
+pub(crate) struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    leftovers: Mutex<Vec<PathBuf>>,
+}
+
+impl Spool {
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let leftovers = spooled_files(&dir)?.into_iter().map(|(path, _)| path).collect();
+        Ok(Self {
+            dir,
+            max_bytes,
+            leftovers: Mutex::new(leftovers),
+        })
+    }
+
+    /// Persist a serialized batch, evicting the oldest files to stay under the
+    /// size cap. Bodies larger than the whole cap are not spooled.
+    pub async fn write(self: &Arc<Self>, kind: BatchKind, body: Vec<u8>) -> Option<PathBuf> {
+        let spool = self.clone();
+        tokio::task::spawn_blocking(move || spool.write_blocking(kind, &body))
+            .await
+            .ok()?
+            .ok()
+            .flatten()
+    }
+
+    fn write_blocking(&self, kind: BatchKind, body: &[u8]) -> io::Result<Option<PathBuf>> {
+        let size = body.len() as u64;
+        if size > self.max_bytes {
+            return Ok(None);
+        }
+
+        let files = spooled_files(&self.dir)?;
+        let mut total: u64 = files.iter().map(|(_, len)| len).sum();
+        for (path, len) in files {
+            if total + size <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let name = format!("{millis:013}-{}{}", uuid::Uuid::new_v4(), kind.suffix());
+        let path = self.dir.join(name);
+
+        // Write under a temporary name so a crash never leaves half a batch.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, body)?;
+        fs::rename(&tmp, &path)?;
+        Ok(Some(path))
+    }
+
+    pub async fn remove(&self, path: PathBuf) {
+        let _ = tokio::task::spawn_blocking(move || fs::remove_file(path)).await;
+    }
+
+    /// Files to replay, oldest first. Holding the guard keeps concurrent
+    /// replays from sending the same file twice.
+    pub async fn leftovers(&self) -> MutexGuard<'_, Vec<PathBuf>> {
+        self.leftovers.lock().await
+    }
+
+    /// Read a spooled batch back, returning its kind and JSON.
+    pub async fn read(&self, path: &Path) -> io::Result<(BatchKind, Vec<u8>)> {
+        let kind = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(BatchKind::from_file_name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a spool file"))?;
+        let path = path.to_path_buf();
+        let json = tokio::task::spawn_blocking(move || fs::read(path))
+            .await
+            .map_err(io::Error::other)??;
+        Ok((kind, json))
+    }
+}
+
+/// Spooled batch files in `dir` with their sizes, oldest first.
+fn spooled_files(dir: &Path) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let is_batch = name
+            .to_str()
+            .and_then(BatchKind::from_file_name)
+            .is_some();
+        if is_batch {
+            files.push((entry.path(), entry.metadata()?.len()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/// Crates treated as library code when no in-app prefixes are configured.
+const NOT_IN_APP: &[&str] = &[
+    "std", "core", "alloc", "backtrace", "tokio", "futures", "futures_util",
+    "async_trait", "anyhow", "eyre", "bloop_client", "__rustc",
+];
+
+/// One frame of a parsed backtrace, innermost first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackFrame {
+    /// Symbol name as printed in the backtrace.
+    pub function: String,
+    /// Path of the module (or type) the function belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    /// Whether the frame is application code rather than a dependency.
+    pub in_app: bool,
+}
+
+/// Parse the text of a `std::backtrace::Backtrace` (or the `backtrace`
+/// crate, which uses the same layout) into frames.
+///
+/// A frame is in-app when its module starts with one of `in_app_prefixes`
+/// at a `::` boundary; with no prefixes, every frame outside the standard
+/// library and this SDK's own dependencies is. Frames without a module path,
+/// such as C symbols, are never in-app. Text in any other format yields no
+/// frames.
+pub(crate) fn parse_frames(stack: &str, in_app_prefixes: &[String]) -> Vec<StackFrame> {
+    let mut frames: Vec<StackFrame> = Vec::new();
+    for line in stack.lines().map(str::trim) {
+        if let Some(location) = line.strip_prefix("at ") {
+            if let Some(frame) = frames.last_mut() {
+                let (file, line, column) = parse_location(location);
+                frame.file = Some(file.to_string());
+                frame.line = line;
+                frame.column = column;
+            }
+            continue;
+        }
+
+        let Some((index, function)) = line.split_once(": ") else {
+            continue;
+        };
+        if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        let module = module_of(function);
+        frames.push(StackFrame {
+            function: function.to_string(),
+            in_app: module
+                .as_deref()
+                .is_some_and(|module| is_in_app(module, in_app_prefixes)),
+            module,
+            file: None,
+            line: None,
+            column: None,
+        });
+    }
+    frames
+}
+
+/// Split `file:line:column`, keeping any colons inside the file name.
+fn parse_location(location: &str) -> (&str, Option<u32>, Option<u32>) {
+    let parts: Vec<&str> = location.rsplitn(3, ':').collect();
+    match parts[..] {
+        [column, line, file] => match (line.parse().ok(), column.parse().ok()) {
+            (Some(line), Some(column)) => (file, Some(line), Some(column)),
+            _ => (location, None, None),
+        },
+        _ => (location, None, None),
+    }
+}
+
+/// The path a symbol belongs to: `a::b` for `a::b::f`, and the type's module
+/// for trait impls like `<a::b::T as Trait>::f`.
+fn module_of(function: &str) -> Option<String> {
+    let path: String = function
+        .trim_start_matches('<')
+        .chars()
+        .take_while(|c| !matches!(c, ' ' | '<' | '>' | '('))
+        .collect();
+    let mut segments: Vec<&str> = path
+        .split("::")
+        .filter(|s| !s.is_empty() && !s.starts_with("{{"))
+        .collect();
+    segments.pop();
+    (!segments.is_empty()).then(|| segments.join("::"))
+}
+
+fn is_in_app(path: &str, prefixes: &[String]) -> bool {
+    let matches = |prefix: &str| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    };
+    if prefixes.is_empty() {
+        !NOT_IN_APP.iter().any(|krate| matches(krate))
+    } else {
+        prefixes.iter().any(|prefix| matches(prefix))
+    }
+}
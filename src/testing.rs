@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use crate::client::BloopClient;
+use crate::event::IngestEvent;
+use crate::transport::{SendOutcome, Transport};
+
+#[cfg(feature = "tracing")]
+use crate::tracing::Trace;
+
+/// A transport that keeps every batch in memory instead of sending it.
+///
+/// Clones share the same recording, so keep one handle for assertions and
+/// give another to the client. Items show up once the client uploads them,
+/// i.e. after a full batch or a `flush()`.
+///
+/// ```no_run
+/// # async fn run() {
+/// let recorder = bloop_client::RecordingTransport::new();
+/// let client = recorder.client();
+/// client.capture_error("PaymentDeclined", "card expired");
+/// client.flush().await;
+/// recorder.assert_event("PaymentDeclined");
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordingTransport {
+    events: Arc<Mutex<Vec<IngestEvent>>>,
+    #[cfg(feature = "tracing")]
+    traces: Arc<Mutex<Vec<Trace>>>,
+}
+
+impl RecordingTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A client that delivers to this recording.
+    pub fn client(&self) -> BloopClient {
+        BloopClient::builder()
+            .transport(self.clone())
+            .build()
+            .expect("a client with a custom transport always builds")
+    }
+
+    /// Every event recorded so far, oldest first.
+    pub fn events(&self) -> Vec<IngestEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Recorded events with the given error type.
+    pub fn events_of_type(&self, error_type: &str) -> Vec<IngestEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.error_type == error_type)
+            .cloned()
+            .collect()
+    }
+
+    /// Assert that an event with this error type was recorded, returning the
+    /// most recent one.
+    #[track_caller]
+    pub fn assert_event(&self, error_type: &str) -> IngestEvent {
+        match self.events_of_type(error_type).pop() {
+            Some(event) => event,
+            None => panic!(
+                "expected an event of type {error_type:?}, recorded types: {:?}",
+                self.recorded_types()
+            ),
+        }
+    }
+
+    /// Assert that an event with this error type and a message containing
+    /// `text` was recorded.
+    #[track_caller]
+    pub fn assert_event_message(&self, error_type: &str, text: &str) -> IngestEvent {
+        let found = self
+            .events_of_type(error_type)
+            .into_iter()
+            .rfind(|e| e.message.contains(text));
+        match found {
+            Some(event) => event,
+            None => panic!(
+                "expected an event of type {error_type:?} with message containing {text:?}, recorded: {:?}",
+                self.events()
+                    .iter()
+                    .map(|e| (e.error_type.clone(), e.message.clone()))
+                    .collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_no_events(&self) {
+        let types = self.recorded_types();
+        assert!(types.is_empty(), "expected no events, recorded types: {types:?}");
+    }
+
+    /// Forget everything recorded so far.
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+        #[cfg(feature = "tracing")]
+        self.traces.lock().unwrap().clear();
+    }
+
+    fn recorded_types(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.error_type.clone())
+            .collect()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl RecordingTransport {
+    /// Every trace recorded so far, oldest first.
+    pub fn traces(&self) -> Vec<Trace> {
+        self.traces.lock().unwrap().clone()
+    }
+
+    /// Assert that a trace with this name was recorded, returning the most
+    /// recent one.
+    #[track_caller]
+    pub fn assert_trace(&self, name: &str) -> Trace {
+        let traces = self.traces();
+        match traces.iter().rfind(|t| t.name == name) {
+            Some(trace) => trace.clone(),
+            None => panic!(
+                "expected a trace named {name:?}, recorded: {:?}",
+                traces.iter().map(|t| &t.name).collect::<Vec<_>>()
+            ),
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_no_traces(&self) {
+        let traces = self.traces();
+        assert!(
+            traces.is_empty(),
+            "expected no traces, recorded: {:?}",
+            traces.iter().map(|t| &t.name).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn send_errors(&self, events: &[IngestEvent]) -> SendOutcome {
+        self.events.lock().unwrap().extend_from_slice(events);
+        SendOutcome::Delivered
+    }
+
+    #[cfg(feature = "tracing")]
+    async fn send_traces(&self, traces: &[Trace]) -> SendOutcome {
+        self.traces.lock().unwrap().extend_from_slice(traces);
+        SendOutcome::Delivered
+    }
+}
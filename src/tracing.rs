@@ -1,7 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use crate::client::BloopClient;
+use crate::scrub::Redactions;
 use crate::tracing_types::{SpanType, SpanStatus, TraceStatus};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Span {
     pub id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -99,7 +101,7 @@ impl Span {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trace {
     pub id: String,
     pub name: String,
@@ -122,6 +124,20 @@ pub struct Trace {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ended_at: Option<i64>,
     pub spans: Vec<Span>,
+    /// Whether sampling kept this trace. Unsampled traces are not sent.
+    #[serde(default = "kept")]
+    pub sampled: bool,
+    /// Rate the sampling decision was made at, so the server can
+    /// extrapolate. `None` until the client has decided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    /// Redactions made by the scrubber across the trace and its spans.
+    #[serde(default, skip_serializing_if = "Redactions::is_empty")]
+    pub redactions: Redactions,
+}
+
+fn kept() -> bool {
+    true
 }
 
 impl Trace {
@@ -140,6 +156,9 @@ impl Trace {
             started_at: chrono_millis(),
             ended_at: None,
             spans: Vec::new(),
+            sampled: true,
+            sample_rate: None,
+            redactions: Redactions::new(),
         }
     }
 
@@ -153,6 +172,13 @@ impl Trace {
         self
     }
 
+    /// Set `user_id` to the hash of a raw user id, from
+    /// [`BloopClient::hash_user_id`]. The raw id is not kept.
+    pub fn hashed_user_id(mut self, client: &BloopClient, user_id: &str) -> Self {
+        self.user_id = Some(client.hash_user_id(user_id));
+        self
+    }
+
     pub fn input_text(mut self, input: impl Into<String>) -> Self {
         self.input = Some(input.into());
         self
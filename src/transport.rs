@@ -0,0 +1,179 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use crate::compression::Compression;
+use crate::error::BloopError;
+use crate::event::IngestEvent;
+use crate::retry::RetryPolicy;
+use crate::signing;
+
+#[cfg(feature = "tracing")]
+use crate::tracing::Trace;
+
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub(crate) const DEFAULT_USER_AGENT: &str = concat!("bloop-rust/", env!("CARGO_PKG_VERSION"));
+
+/// Result of handing one batch to a [`Transport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// The batch was accepted.
+    Delivered,
+    /// The receiver asked us to slow down. All uploads pause for
+    /// `retry_after`, or for the retry backoff when none was given.
+    RateLimited { retry_after: Option<Duration> },
+    /// A transient failure such as a network error or 5xx; worth retrying.
+    Failed(BloopError),
+    /// The batch was refused and retrying it will not help.
+    Rejected(BloopError),
+}
+
+/// Delivers batches of events and traces.
+///
+/// The client handles buffering, retries, rate-limit pauses and spooling
+/// around the transport; an implementation only has to attempt one send and
+/// report what happened.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send_errors(&self, events: &[IngestEvent]) -> SendOutcome;
+
+    /// Rejects traces unless overridden, so implementations written without
+    /// the `tracing` feature keep compiling when another crate enables it.
+    #[cfg(feature = "tracing")]
+    async fn send_traces(&self, traces: &[Trace]) -> SendOutcome {
+        let _ = traces;
+        SendOutcome::Rejected(BloopError::InvalidConfig {
+            setting: "transport",
+            reason: "this transport does not send traces".into(),
+        })
+    }
+}
+
+/// The default transport: signed JSON POSTs to the bloop ingest endpoints.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    http: reqwest::Client,
+    endpoint: String,
+    project_key: String,
+    compression: Compression,
+}
+
+impl HttpTransport {
+    /// A transport with the same defaults as the client builder: a 10 second
+    /// connect timeout, a 30 second request timeout and a `bloop-rust/<version>`
+    /// user agent. Use [`with_client`](Self::with_client) to change them.
+    pub fn new(endpoint: impl Into<String>, project_key: impl Into<String>) -> Self {
+        let http = reqwest::Client::builder()
+            .connect_timeout(DEFAULT_CONNECT_TIMEOUT)
+            .timeout(DEFAULT_REQUEST_TIMEOUT)
+            .user_agent(DEFAULT_USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self::with_client(http, endpoint, project_key)
+    }
+
+    pub fn with_client(
+        http: reqwest::Client,
+        endpoint: impl Into<String>,
+        project_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            project_key: project_key.into(),
+            compression: Compression::default(),
+        }
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    async fn post(&self, path: &str, payload: serde_json::Value) -> SendOutcome {
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => return SendOutcome::Rejected(BloopError::Serialization(e.to_string())),
+        };
+        let body = match self.compression.encode(body) {
+            Ok(body) => body,
+            Err(e) => return SendOutcome::Rejected(BloopError::Serialization(e.to_string())),
+        };
+        let signature = signing::sign(&self.project_key, &body);
+        let url = format!("{}{path}", self.endpoint);
+
+        let mut request = self
+            .http
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("X-Signature", signature)
+            .header("X-Project-Key", &self.project_key);
+        if let Some(encoding) = self.compression.content_encoding() {
+            request = request.header("Content-Encoding", encoding);
+        }
+
+        match request.body(body).send().await {
+            Ok(resp) if resp.status().is_success() => SendOutcome::Delivered,
+            Ok(resp) => {
+                let status = resp.status();
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                match (status.as_u16(), retry_after) {
+                    (429, _) | (503, Some(_)) => SendOutcome::RateLimited { retry_after },
+                    (status @ (401 | 403), _) => {
+                        SendOutcome::Rejected(BloopError::Unauthorized { status })
+                    }
+                    (status, _) if RetryPolicy::is_retryable_status(status) => {
+                        SendOutcome::Failed(BloopError::Http { status })
+                    }
+                    (status, _) => SendOutcome::Rejected(BloopError::Http { status }),
+                }
+            }
+            Err(e) if e.is_builder() => SendOutcome::Rejected(BloopError::InvalidEndpoint {
+                url,
+                reason: e.to_string(),
+            }),
+            Err(e) => SendOutcome::Failed(BloopError::Network(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send_errors(&self, events: &[IngestEvent]) -> SendOutcome {
+        self.post("/v1/ingest/batch", serde_json::json!({ "events": events }))
+            .await
+    }
+
+    #[cfg(feature = "tracing")]
+    async fn send_traces(&self, traces: &[Trace]) -> SendOutcome {
+        self.post("/v1/traces/batch", serde_json::json!({ "traces": traces }))
+            .await
+    }
+}
+
+/// Transport for disabled clients, which never hand it anything.
+pub(crate) struct NoopTransport;
+
+#[async_trait]
+impl Transport for NoopTransport {
+    async fn send_errors(&self, _events: &[IngestEvent]) -> SendOutcome {
+        SendOutcome::Delivered
+    }
+
+    #[cfg(feature = "tracing")]
+    async fn send_traces(&self, _traces: &[Trace]) -> SendOutcome {
+        SendOutcome::Delivered
+    }
+}
+
+/// Parse a `Retry-After` value given either as seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value.trim()).ok()?;
+    Some(at.duration_since(std::time::SystemTime::now()).unwrap_or_default())
+}
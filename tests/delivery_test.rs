@@ -0,0 +1,799 @@
+mod common;
+
+use bloop_client::*;
+use common::MockServer;
+use std::time::Duration;
+
+fn fast_retry(attempts: u32) -> RetryPolicy {
+    RetryPolicy::new()
+        .max_attempts(attempts)
+        .base_delay(Duration::from_millis(1))
+        .max_delay(Duration::from_millis(5))
+}
+
+#[tokio::test]
+async fn test_retries_server_errors() {
+    let server = MockServer::start(vec![(503, vec![]), (502, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(fast_retry(3))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|r| r.path == "/v1/ingest/batch"));
+    assert_eq!(requests[0].body, requests[2].body);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_attempts() {
+    let server = MockServer::start(vec![(500, vec![]); 5]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(fast_retry(2))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_does_not_retry_client_errors() {
+    let server = MockServer::start(vec![(422, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(fast_retry(5))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_after_delays_next_attempt() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "1".into())])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(fast_retry(2).max_delay(Duration::from_secs(2)))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    let started = std::time::Instant::now();
+    client.flush().await;
+
+    assert_eq!(server.requests().len(), 2);
+    assert!(started.elapsed() >= Duration::from_millis(900));
+}
+
+#[tokio::test]
+async fn test_rate_limited_batch_is_buffered_until_pause_ends() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "1".into())])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    // Longer than the retry policy waits, and out of attempts: the batch
+    // goes back into the buffer rather than being dropped.
+    client.capture_error("Error", "msg");
+    let report = client.flush().await;
+    assert!(report.failures.is_empty());
+    assert_eq!(report.pending, 1);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let report = client.flush().await;
+    assert_eq!(report.events_sent, 1);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_rate_limit_pauses_uploads_from_capture() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "30".into())])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "first");
+    client.flush().await;
+    assert_eq!(server.requests().len(), 1);
+
+    // Well past the batch threshold, but uploads are paused.
+    for i in 0..50 {
+        client.capture_error("Error", format!("msg {i}"));
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_long_retry_after_does_not_stall_shutdown() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "3600".into())])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "first");
+    let report = client.flush().await;
+    assert_eq!(report.pending, 1);
+
+    // Uploads are paused, so shutdown gives up at once and reports the
+    // buffered events as pending instead of sleeping through the pause.
+    client.capture_error("Error", "second");
+    let report = client.shutdown_timeout(Duration::from_secs(1)).await;
+    assert!(!report.timed_out);
+    assert_eq!(report.pending, 2);
+    assert_eq!(server.requests().len(), 1);
+}
+
+fn spool_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("bloop-spool-{}", uuid::Uuid::new_v4()))
+}
+
+fn spool_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_spool_removes_delivered_batches() {
+    let server = MockServer::start(vec![]).await;
+    let dir = spool_dir();
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .spool(&dir, 1 << 20)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(server.requests().len(), 1);
+    assert!(spool_files(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_spool_replays_leftovers_on_build() {
+    let dir = spool_dir();
+    let failing = MockServer::start(vec![(500, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&failing.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .spool(&dir, 1 << 20)
+        .build()
+        .unwrap();
+    client.capture_error("Error", "survives");
+    client.flush().await;
+    assert_eq!(spool_files(&dir).len(), 1);
+
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .spool(&dir, 1 << 20)
+        .build()
+        .unwrap();
+    client.flush().await;
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/ingest/batch");
+    assert_eq!(requests[0].body, failing.requests()[0].body);
+    assert!(spool_files(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_spool_replays_failed_batches_on_next_flush() {
+    let dir = spool_dir();
+    let server = MockServer::start(vec![(500, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .spool(&dir, 1 << 20)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "retried later");
+    let report = client.flush().await;
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(spool_files(&dir).len(), 1);
+
+    // Same process: the failed batch is replayed without a restart.
+    let report = client.flush().await;
+    assert_eq!(report.events_sent, 1);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].body, requests[0].body);
+    assert!(spool_files(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_spool_respects_size_cap() {
+    let dir = spool_dir();
+    let server = MockServer::start(vec![(500, vec![]); 2]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .spool(&dir, 16)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "too big for the spool");
+    client.flush().await;
+
+    assert!(spool_files(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_flush_interval_sends_without_explicit_flush() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .flush_interval(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(server.requests().len(), 1);
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_worker_flushes_on_size_threshold() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .flush_interval(Duration::from_secs(3600))
+        .build()
+        .unwrap();
+
+    for i in 0..20 {
+        client.capture_error("Error", format!("msg {i}"));
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(server.requests().len(), 1);
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_worker() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .flush_interval(Duration::from_millis(20))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "before shutdown");
+    client.shutdown().await;
+    let delivered = server.requests().len();
+    assert_eq!(delivered, 1);
+
+    client.capture_error("Error", "after shutdown");
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(server.requests().len(), delivered);
+}
+
+async fn paused_client(server: &MockServer, policy: OverflowPolicy) -> BloopClient {
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .max_queue_size(25)
+        .overflow_policy(policy)
+        .build()
+        .unwrap();
+    client.capture_error("Error", "trigger rate limit");
+    client.flush().await;
+    client
+}
+
+#[tokio::test]
+async fn test_drop_newest_keeps_first_items() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "1".into())])]).await;
+    let client = paused_client(&server, OverflowPolicy::DropNewest).await;
+
+    for i in 0..40 {
+        client.capture_error("Error", format!("msg {i}"));
+    }
+    // The rate-limited "trigger" event was buffered again and holds a slot.
+    assert_eq!(client.dropped_count(), 16);
+
+    // Wait out the pause; a flush while paused sends nothing.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    client.flush().await;
+    let body = String::from_utf8(server.requests()[1].body.clone()).unwrap();
+    assert!(body.contains("\"trigger rate limit\""));
+    assert!(body.contains("\"msg 0\""));
+    assert!(body.contains("\"msg 23\""));
+    assert!(!body.contains("\"msg 24\""));
+}
+
+#[tokio::test]
+async fn test_drop_oldest_keeps_latest_items() {
+    let server = MockServer::start(vec![(429, vec![("retry-after", "1".into())])]).await;
+    let client = paused_client(&server, OverflowPolicy::DropOldest).await;
+
+    for i in 0..40 {
+        client.capture_error("Error", format!("msg {i}"));
+    }
+    // The rate-limited "trigger" event was buffered again and holds a slot.
+    assert_eq!(client.dropped_count(), 16);
+
+    // Wait out the pause; a flush while paused sends nothing.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    client.flush().await;
+    let body = String::from_utf8(server.requests()[1].body.clone()).unwrap();
+    assert!(!body.contains("\"trigger rate limit\""));
+    assert!(!body.contains("\"msg 14\""));
+    assert!(body.contains("\"msg 15\""));
+    assert!(body.contains("\"msg 39\""));
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn test_gzip_compression_signs_sent_bytes() {
+    use hmac::{Hmac, Mac};
+    use std::io::Read;
+
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .compression(Compression::Gzip)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "compressed");
+    client.flush().await;
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("content-encoding"), Some("gzip"));
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"test-key").unwrap();
+    mac.update(&request.body);
+    assert_eq!(request.header("x-signature"), Some(hex::encode(mac.finalize().into_bytes()).as_str()));
+
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(request.body.as_slice())
+        .read_to_string(&mut json)
+        .unwrap();
+    assert!(json.contains("\"message\":\"compressed\""));
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn test_zstd_compression() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .compression(Compression::Zstd)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "compressed");
+    client.flush().await;
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("content-encoding"), Some("zstd"));
+    let json = zstd::decode_all(request.body.as_slice()).unwrap();
+    assert!(String::from_utf8(json).unwrap().contains("\"message\":\"compressed\""));
+}
+
+#[tokio::test]
+async fn test_uncompressed_by_default() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "plain");
+    client.flush().await;
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("content-encoding"), None);
+    assert!(String::from_utf8_lossy(&request.body).contains("\"message\":\"plain\""));
+}
+
+/// Transport that records batches and answers from a script.
+struct ScriptedTransport {
+    outcomes: std::sync::Mutex<Vec<SendOutcome>>,
+    events: std::sync::Arc<std::sync::Mutex<Vec<IngestEvent>>>,
+    calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+impl ScriptedTransport {
+    fn new(mut outcomes: Vec<SendOutcome>) -> Self {
+        outcomes.reverse();
+        Self {
+            outcomes: std::sync::Mutex::new(outcomes),
+            events: Default::default(),
+            calls: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ScriptedTransport {
+    async fn send_errors(&self, events: &[IngestEvent]) -> SendOutcome {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let outcome = self.outcomes.lock().unwrap().pop().unwrap_or(SendOutcome::Delivered);
+        if outcome == SendOutcome::Delivered {
+            self.events.lock().unwrap().extend_from_slice(events);
+        }
+        outcome
+    }
+
+    #[cfg(feature = "tracing")]
+    async fn send_traces(&self, _traces: &[Trace]) -> SendOutcome {
+        SendOutcome::Delivered
+    }
+}
+
+/// Transport written without the `tracing` feature in mind.
+#[cfg(feature = "tracing")]
+struct ErrorsOnlyTransport;
+
+#[cfg(feature = "tracing")]
+#[async_trait::async_trait]
+impl Transport for ErrorsOnlyTransport {
+    async fn send_errors(&self, _events: &[IngestEvent]) -> SendOutcome {
+        SendOutcome::Delivered
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_transport_without_send_traces_rejects_traces() {
+    let client = BloopClient::builder()
+        .transport(ErrorsOnlyTransport)
+        .retry_policy(fast_retry(3))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.send_trace(client.start_trace("chat"));
+    let report = client.flush().await;
+
+    assert_eq!(report.events_sent, 1);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].kind, BatchKind::Traces);
+}
+
+#[tokio::test]
+async fn test_custom_transport_needs_no_endpoint() {
+    let transport = ScriptedTransport::new(vec![]);
+    let events = transport.events.clone();
+    let client = BloopClient::builder()
+        .environment("test")
+        .transport(transport)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "via transport");
+    client.flush().await;
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message, "via transport");
+    assert_eq!(events[0].environment, "test");
+}
+
+#[tokio::test]
+async fn test_block_policy_gives_up_when_upload_cannot_progress() {
+    let transport = ScriptedTransport::new(vec![]);
+    let events = transport.events.clone();
+    let client = BloopClient::builder()
+        .transport(transport)
+        .max_buffer_size(1)
+        .max_queue_size(1)
+        .overflow_policy(OverflowPolicy::Block)
+        .build()
+        .unwrap();
+
+    // The first upload is spawned on this current-thread runtime, so it
+    // cannot finish while the second capture blocks; the wait is bounded.
+    client.capture_error("Error", "first");
+    let started = std::time::Instant::now();
+    client.capture_error("Error", "second");
+    assert!(started.elapsed() >= Duration::from_millis(900));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(client.dropped_count(), 1);
+
+    // Let the spawned upload run.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message, "first");
+}
+
+#[tokio::test]
+async fn test_transport_failures_are_retried() {
+    let transport = ScriptedTransport::new(vec![
+        SendOutcome::Failed(BloopError::Network("reset".into())),
+        SendOutcome::RateLimited { retry_after: Some(Duration::from_millis(2)) },
+    ]);
+    let (events, calls) = (transport.events.clone(), transport.calls.clone());
+    let client = BloopClient::builder()
+        .transport(transport)
+        .retry_policy(fast_retry(3))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    assert_eq!(events.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_transport_rejections_are_not_retried() {
+    let transport = ScriptedTransport::new(vec![SendOutcome::Rejected(BloopError::Http { status: 400 })]);
+    let calls = transport.calls.clone();
+    let client = BloopClient::builder()
+        .transport(transport)
+        .retry_policy(fast_retry(3))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_request_timeout_bounds_flush() {
+    // Accepts connections but never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let client = BloopClient::builder()
+        .endpoint(url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .request_timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    let started = std::time::Instant::now();
+    client.flush().await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_user_agent_header() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .user_agent("my-service/2.0")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(server.requests()[0].header("user-agent"), Some("my-service/2.0"));
+}
+
+#[tokio::test]
+async fn test_default_user_agent_names_the_sdk() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    let request = &server.requests()[0];
+    assert!(request.header("user-agent").unwrap().starts_with("bloop-rust/"));
+}
+
+#[tokio::test]
+async fn test_standalone_http_transport_names_the_sdk() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .transport(HttpTransport::new(&server.url, "test-key"))
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    let request = &server.requests()[0];
+    assert!(request.header("user-agent").unwrap().starts_with("bloop-rust/"));
+}
+
+#[tokio::test]
+async fn test_requests_go_through_proxy() {
+    let proxy = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint("http://bloop.invalid")
+        .project_key("test-key")
+        .proxy(&proxy.url)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    let requests = proxy.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "http://bloop.invalid/v1/ingest/batch");
+}
+
+#[tokio::test]
+async fn test_custom_http_client() {
+    let server = MockServer::start(vec![]).await;
+    let http = reqwest::Client::builder()
+        .user_agent("preconfigured")
+        .build()
+        .unwrap();
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .user_agent("ignored")
+        .http_client(http)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+
+    assert_eq!(server.requests()[0].header("user-agent"), Some("preconfigured"));
+}
+
+#[tokio::test]
+async fn test_flush_reports_delivered_counts() {
+    let server = MockServer::start(vec![]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "one");
+    client.capture_error("Error", "two");
+    let report = client.flush().await;
+
+    assert_eq!(report.events_sent, 2);
+    assert_eq!(report.traces_sent, 0);
+    assert!(report.is_complete());
+}
+
+#[tokio::test]
+async fn test_flush_reports_failures() {
+    let server = MockServer::start(vec![(422, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("test-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "one");
+    let report = client.flush().await;
+
+    assert_eq!(report.events_sent, 0);
+    assert_eq!(report.failures.len(), 1);
+    let failure = &report.failures[0];
+    assert_eq!(failure.kind, BatchKind::Events);
+    assert_eq!(failure.items, 1);
+    assert_eq!(failure.error, BloopError::Http { status: 422 });
+    assert!(!report.is_complete());
+}
+
+#[tokio::test]
+async fn test_shutdown_timeout_gives_up() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            held.push(stream);
+        }
+    });
+
+    let client = BloopClient::builder()
+        .endpoint(url)
+        .project_key("test-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "one");
+    client.capture_error("Error", "two");
+    let started = std::time::Instant::now();
+    let report = client.shutdown_timeout(Duration::from_millis(200)).await;
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(report.timed_out);
+    assert_eq!(report.pending, 2);
+    assert_eq!(report.events_sent, 0);
+}
+
+#[tokio::test]
+async fn test_unauthorized_key_is_reported() {
+    let server = MockServer::start(vec![(401, vec![])]).await;
+    let client = BloopClient::builder()
+        .endpoint(&server.url)
+        .project_key("wrong-key")
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    let report = client.flush().await;
+
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(report.failures[0].error, BloopError::Unauthorized { status: 401 });
+}
+
+#[tokio::test]
+async fn test_network_errors_are_reported() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let client = BloopClient::builder()
+        .endpoint(url)
+        .project_key("test-key")
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    let report = client.flush().await;
+
+    assert!(matches!(report.failures[0].error, BloopError::Network(_)));
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use bloop_client::*;
+use common::MockServer;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// The panic hook is process-wide, so these tests live in their own binary
+// and take turns installing it.
+static HOOK: Mutex<()> = Mutex::const_new(());
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_hook_reports_and_flushes() {
+    let _hook = HOOK.lock().await;
+    let server = MockServer::start(vec![]).await;
+    let client = Arc::new(
+        BloopClient::builder()
+            .endpoint(&server.url)
+            .project_key("test-key")
+            .build()
+            .unwrap(),
+    );
+    client.install_panic_hook();
+
+    let result = std::thread::Builder::new()
+        .name("worker-7".into())
+        .spawn(|| panic!("boom at {}", 42))
+        .unwrap()
+        .join();
+    assert!(result.is_err());
+
+    // The hook flushed before returning, so the upload already happened.
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let event = &body["events"][0];
+    assert_eq!(event["error_type"], PANIC_ERROR_TYPE);
+    assert_eq!(event["message"], "boom at 42");
+    assert_eq!(event["level"], "fatal");
+    assert!(!event["stack"].as_str().unwrap().is_empty());
+    assert_eq!(event["metadata"]["thread"], "worker-7");
+    assert_eq!(event["metadata"]["location"]["file"], "tests/panic_test.rs");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_hook_with_full_batch_outside_runtime() {
+    let _hook = HOOK.lock().await;
+    let server = MockServer::start(vec![]).await;
+    let client = Arc::new(
+        BloopClient::builder()
+            .endpoint(&server.url)
+            .project_key("test-key")
+            .max_buffer_size(1)
+            .build()
+            .unwrap(),
+    );
+    client.install_panic_hook();
+
+    // The panic fills the batch on a thread with no tokio runtime; the hook
+    // must buffer it rather than spawn an upload, then flush it itself.
+    let result = std::thread::spawn(|| panic!("full batch")).join();
+    assert!(result.is_err());
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["events"][0]["message"], "full batch");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_panic_hook_inside_configure_scope() {
+    let _hook = HOOK.lock().await;
+    let server = MockServer::start(vec![]).await;
+    let client = Arc::new(
+        BloopClient::builder()
+            .endpoint(&server.url)
+            .project_key("test-key")
+            .build()
+            .unwrap(),
+    );
+    client.install_panic_hook();
+
+    // The hook runs while the scope lock is still held; it must not wait on it.
+    let panicking = client.clone();
+    let result = std::thread::spawn(move || {
+        panicking.configure_scope(|_| panic!("inside scope"));
+    })
+    .join();
+    assert!(result.is_err());
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["events"][0]["message"], "inside scope");
+
+    // The poisoned scope stays usable.
+    client.configure_scope(|scope| scope.set_tag("after", "panic"));
+}
@@ -1,4 +1,5 @@
 use bloop_client::*;
+use std::time::Duration;
 
 #[test]
 fn test_event_creation() {
@@ -26,6 +27,8 @@ fn test_event_with_all_fields() {
         request_id: Some("req-123".into()),
         user_id_hash: Some("abc123".into()),
         metadata: Some(serde_json::json!({"key": "value"})),
+        fingerprint: Some("users-timeout".into()),
+        level: Some(Level::Warning),
     };
     assert_eq!(event.http_status, Some(500));
     assert_eq!(event.source.as_deref(), Some("api-server"));
@@ -196,7 +199,7 @@ fn test_builder_requires_endpoint() {
         .project_key("key")
         .build();
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("endpoint"));
+    assert_eq!(result.unwrap_err(), BloopError::MissingConfig("endpoint"));
 }
 
 #[test]
@@ -205,7 +208,7 @@ fn test_builder_requires_key() {
         .endpoint("http://localhost")
         .build();
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("project_key"));
+    assert_eq!(result.unwrap_err(), BloopError::MissingConfig("project_key"));
 }
 
 #[test]
@@ -276,3 +279,1168 @@ async fn test_shutdown() {
     // Shutdown should not panic
     client.shutdown().await;
 }
+
+#[test]
+fn test_retry_backoff_is_exponential_and_capped() {
+    let policy = RetryPolicy::new()
+        .base_delay(Duration::from_millis(100))
+        .max_delay(Duration::from_millis(350));
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(350));
+    assert_eq!(policy.backoff(40), Duration::from_millis(350));
+}
+
+#[test]
+fn test_retry_jitter_stays_within_backoff() {
+    let policy = RetryPolicy::new()
+        .base_delay(Duration::from_millis(100))
+        .jitter(0.5);
+    for _ in 0..100 {
+        let delay = policy.delay(1);
+        assert!(delay >= Duration::from_millis(50));
+        assert!(delay <= Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn test_retryable_statuses() {
+    assert!(RetryPolicy::is_retryable_status(500));
+    assert!(RetryPolicy::is_retryable_status(503));
+    assert!(RetryPolicy::is_retryable_status(429));
+    assert!(RetryPolicy::is_retryable_status(408));
+    assert!(!RetryPolicy::is_retryable_status(400));
+    assert!(!RetryPolicy::is_retryable_status(401));
+    assert!(!RetryPolicy::is_retryable_status(422));
+}
+
+#[test]
+fn test_retry_after_is_clamped() {
+    let policy = RetryPolicy::new();
+    assert_eq!(policy.retry_after(Duration::from_secs(60)), Duration::from_secs(60));
+    assert_eq!(policy.retry_after(Duration::from_secs(3600)), Duration::from_secs(600));
+
+    let policy = policy.max_retry_after(Duration::from_secs(30));
+    assert_eq!(policy.retry_after(Duration::from_secs(60)), Duration::from_secs(30));
+}
+
+#[test]
+fn test_retry_none_is_single_attempt() {
+    assert_eq!(RetryPolicy::none().attempts(), 1);
+    assert_eq!(RetryPolicy::new().max_attempts(0).attempts(), 1);
+}
+
+#[test]
+fn test_flush_interval_requires_runtime() {
+    let result = BloopClient::builder()
+        .endpoint("http://localhost:3000")
+        .project_key("key")
+        .flush_interval(Duration::from_secs(5))
+        .build();
+    assert!(matches!(
+        result.unwrap_err(),
+        BloopError::InvalidConfig { setting: "flush_interval", .. }
+    ));
+}
+
+#[test]
+fn test_dropped_count_starts_at_zero() {
+    let client = BloopClient::builder()
+        .endpoint("http://localhost:9999")
+        .project_key("test-key")
+        .build()
+        .unwrap();
+    client.capture_error("Error", "msg");
+    assert_eq!(client.dropped_count(), 0);
+}
+
+#[tokio::test]
+async fn test_recording_transport_captures_events() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    client.capture_error("PaymentDeclined", "card expired");
+    client.capture(Event {
+        error_type: "Timeout".into(),
+        message: "upstream took too long".into(),
+        http_status: Some(504),
+        ..Default::default()
+    });
+    recorder.assert_no_events();
+    client.flush().await;
+
+    assert_eq!(recorder.events().len(), 2);
+    recorder.assert_event_message("PaymentDeclined", "expired");
+    assert_eq!(recorder.assert_event("Timeout").http_status, Some(504));
+    assert!(recorder.events_of_type("Missing").is_empty());
+
+    recorder.clear();
+    recorder.assert_no_events();
+}
+
+#[test]
+#[should_panic(expected = "expected an event of type \"Missing\"")]
+fn test_recording_transport_assert_event_panics() {
+    RecordingTransport::new().assert_event("Missing");
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_recording_transport_captures_traces() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    let mut trace = client.start_trace("chat");
+    trace.end(TraceStatus::Completed);
+    client.send_trace(trace);
+    client.flush().await;
+
+    let trace = recorder.assert_trace("chat");
+    assert!(matches!(trace.status, TraceStatus::Completed));
+    recorder.assert_no_events();
+}
+
+#[test]
+fn test_builder_rejects_invalid_root_certificate() {
+    let result = BloopClient::builder()
+        .endpoint("http://localhost:3000")
+        .project_key("key")
+        .root_certificate_pem("not a certificate")
+        .build();
+    assert!(matches!(
+        result.unwrap_err(),
+        BloopError::InvalidConfig { setting: "root certificate", .. }
+    ));
+}
+
+#[test]
+fn test_builder_rejects_invalid_proxy() {
+    let result = BloopClient::builder()
+        .endpoint("http://localhost:3000")
+        .project_key("key")
+        .proxy("::not a url::")
+        .build();
+    assert!(matches!(
+        result.unwrap_err(),
+        BloopError::InvalidConfig { setting: "proxy", .. }
+    ));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_shutdown_reports_events_and_traces() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    client.capture_error("Error", "msg");
+    client.send_trace(client.start_trace("chat"));
+    let report = client.shutdown().await;
+
+    assert_eq!(report.events_sent, 1);
+    assert_eq!(report.traces_sent, 1);
+    assert_eq!(report.pending, 0);
+    assert!(report.failures.is_empty());
+    assert!(report.is_complete());
+}
+
+#[test]
+fn test_builder_rejects_invalid_endpoint() {
+    for endpoint in ["not a url", "ftp://example.com", "localhost:3000"] {
+        let result = BloopClient::builder()
+            .endpoint(endpoint)
+            .project_key("key")
+            .build();
+        assert!(
+            matches!(result, Err(BloopError::InvalidEndpoint { .. })),
+            "{endpoint} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_error_messages() {
+    assert_eq!(BloopError::MissingConfig("endpoint").to_string(), "endpoint is required");
+    assert_eq!(
+        BloopError::Unauthorized { status: 401 }.to_string(),
+        "project key rejected (HTTP 401)"
+    );
+    assert_eq!(
+        BloopError::RateLimited { retry_after: Some(Duration::from_secs(30)) }.to_string(),
+        "rate limited, retry after 30s"
+    );
+}
+
+fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: std::collections::HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn test_builder_from_lookup() {
+    let vars = [
+        ("BLOOP_ENDPOINT", "http://localhost:3000"),
+        ("BLOOP_PROJECT_KEY", "env-key"),
+        ("BLOOP_ENVIRONMENT", "staging"),
+    ];
+    let builder = BloopClientBuilder::from_lookup(lookup(&vars)).unwrap();
+    let debug = format!("{builder:?}");
+    assert!(debug.contains("http://localhost:3000"));
+    assert!(debug.contains("staging"));
+    assert!(builder.build().is_ok());
+
+    // Explicit calls win over the environment.
+    let builder = BloopClientBuilder::from_lookup(lookup(&vars))
+        .unwrap()
+        .environment("override");
+    assert!(format!("{builder:?}").contains("override"));
+
+    // Empty values keep the defaults.
+    let builder = BloopClientBuilder::from_lookup(lookup(&[("BLOOP_SOURCE", " ")])).unwrap();
+    assert!(format!("{builder:?}").contains("\"rust\""));
+
+    // A disabled client needs no endpoint.
+    let disabled = lookup(&[("BLOOP_DISABLED", "true")]);
+    assert!(BloopClientBuilder::from_lookup(disabled).unwrap().build().is_ok());
+}
+
+#[test]
+fn test_builder_from_lookup_rejects_bad_values() {
+    let cases = [
+        ("BLOOP_SAMPLE_RATE", "1.5"),
+        ("BLOOP_SAMPLE_RATE", "often"),
+        ("BLOOP_TRACES_SAMPLE_RATE", "-0.1"),
+        ("BLOOP_TRACES_SAMPLE_RATE", "half"),
+        ("BLOOP_MAX_BUFFER_SIZE", "-1"),
+        ("BLOOP_MAX_BUFFER_SIZE", "lots"),
+        ("BLOOP_DISABLED", "maybe"),
+    ];
+    for (name, value) in cases {
+        match BloopClientBuilder::from_lookup(lookup(&[(name, value)])) {
+            Err(BloopError::InvalidConfig { setting, .. }) => assert_eq!(setting, name),
+            other => panic!("{name}={value:?} should be rejected, got {other:?}"),
+        }
+    }
+
+    let valid = [
+        ("BLOOP_SAMPLE_RATE", "0.25"),
+        ("BLOOP_TRACES_SAMPLE_RATE", " 1 "),
+        ("BLOOP_MAX_BUFFER_SIZE", "50"),
+    ];
+    assert!(BloopClientBuilder::from_lookup(lookup(&valid)).is_ok());
+}
+
+#[tokio::test]
+async fn test_builder_from_lookup_applies_settings() {
+    let recorder = RecordingTransport::new();
+    let vars = [
+        ("BLOOP_RELEASE", "1.4.2"),
+        ("BLOOP_SOURCE", "worker"),
+        ("BLOOP_MAX_BUFFER_SIZE", "2"),
+        ("BLOOP_TRACES_SAMPLE_RATE", "0"),
+    ];
+    let client = BloopClientBuilder::from_lookup(lookup(&vars))
+        .unwrap()
+        .transport(recorder.clone())
+        .build()
+        .unwrap();
+
+    // Two events fill the batch, which uploads without a flush.
+    client.capture_error("Error", "one");
+    client.capture_error("Error", "two");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let events = recorder.events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].release, "1.4.2");
+    assert_eq!(events[0].source, "worker");
+
+    #[cfg(feature = "tracing")]
+    assert!(!client.start_trace("chat").sampled);
+}
+
+#[tokio::test]
+async fn test_disabled_client_sends_nothing() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .enabled(false)
+        .build()
+        .unwrap();
+
+    client.capture_error("Error", "msg");
+    client.flush().await;
+    recorder.assert_no_events();
+}
+
+#[tokio::test]
+async fn test_sample_rate_zero_drops_errors() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .sample_rate(0.0)
+        .build()
+        .unwrap();
+
+    for _ in 0..10 {
+        client.capture_error("Error", "msg");
+    }
+    client.flush().await;
+    recorder.assert_no_events();
+}
+
+#[derive(Debug)]
+struct ConfigError {
+    source: std::io::Error,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not load config")
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[test]
+fn test_event_from_error_records_source_chain() {
+    let error = ConfigError {
+        source: std::io::Error::new(std::io::ErrorKind::NotFound, "config.toml missing"),
+    };
+    let event = Event::from_error(&error);
+
+    assert_eq!(event.error_type, "unit_test::ConfigError");
+    assert_eq!(event.message, "could not load config");
+    let causes = &event.metadata.unwrap()["causes"];
+    assert_eq!(causes.as_array().unwrap().len(), 1);
+    assert_eq!(causes[0]["type"], "std::io::error::Error");
+    assert_eq!(causes[0]["message"], "config.toml missing");
+}
+
+#[test]
+fn test_event_from_dyn_error_uses_neutral_type() {
+    let error = "x".parse::<u32>().unwrap_err();
+    let event = Event::from_error(&error as &dyn std::error::Error);
+
+    assert_eq!(event.error_type, "Error");
+    assert!(event.metadata.is_none());
+}
+
+#[derive(Debug)]
+enum LoadError {
+    Parse(std::num::ParseIntError),
+    Other(ConfigError),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not load")
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Parse(error) => Some(error),
+            LoadError::Other(error) => Some(error),
+        }
+    }
+}
+
+#[test]
+fn test_event_cause_types_are_not_variant_names() {
+    let error = LoadError::Parse("x".parse::<u8>().unwrap_err());
+    let event = Event::from_error(&error);
+    assert_eq!(event.error_type, "unit_test::LoadError");
+    let causes = &event.metadata.unwrap()["causes"];
+    assert_eq!(causes[0]["type"], "core::num::error::ParseIntError");
+
+    // Not a recognized std error: neither "ConfigError" nor a guess.
+    let error = LoadError::Other(ConfigError {
+        source: std::io::Error::other("disk"),
+    });
+    let causes = &Event::from_error(&error).metadata.unwrap()["causes"];
+    assert_eq!(causes[0]["type"], "Error");
+    assert_eq!(causes[1]["type"], "std::io::error::Error");
+}
+
+#[tokio::test]
+async fn test_capture_std_error() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    let error = "1.5".parse::<i64>().unwrap_err();
+    client.capture_std_error(&error);
+    client.flush().await;
+
+    recorder.assert_event_message("core::num::error::ParseIntError", "invalid digit");
+}
+
+#[cfg(feature = "anyhow")]
+#[test]
+fn test_event_from_anyhow_error() {
+    use anyhow::Context;
+
+    let error = "abc"
+        .parse::<u8>()
+        .context("reading port")
+        .context("loading config")
+        .unwrap_err();
+    let event = Event::from(&error);
+
+    assert_eq!(event.error_type, "core::num::error::ParseIntError");
+    assert_eq!(event.message, "loading config");
+    let causes = &event.metadata.unwrap()["causes"];
+    assert_eq!(causes[0]["message"], "reading port");
+    assert_eq!(causes[1]["type"], "core::num::error::ParseIntError");
+    assert_eq!(causes[1]["message"], "invalid digit found in string");
+}
+
+#[cfg(feature = "anyhow")]
+#[test]
+fn test_event_from_anyhow_io_error_uses_type_name() {
+    use anyhow::Context;
+
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "config.toml missing");
+    let error = Err::<(), _>(io).context("loading config").unwrap_err();
+    let event = Event::from(error);
+
+    // Not the `Debug` variant name ("Custom").
+    assert_eq!(event.error_type, "std::io::error::Error");
+    let causes = &event.metadata.unwrap()["causes"];
+    assert_eq!(causes[0]["type"], "std::io::error::Error");
+}
+
+#[cfg(feature = "eyre")]
+#[test]
+fn test_event_from_eyre_io_error_uses_type_name() {
+    use eyre::WrapErr;
+
+    let report = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+        .wrap_err("loading config")
+        .unwrap_err();
+
+    // Not the `Debug` variant name ("Kind").
+    assert_eq!(Event::from(&report).error_type, "std::io::error::Error");
+}
+
+#[cfg(feature = "eyre")]
+#[test]
+fn test_event_from_eyre_report() {
+    use eyre::WrapErr;
+
+    let report = "abc"
+        .parse::<u8>()
+        .wrap_err("loading config")
+        .unwrap_err();
+    let event = Event::from(report);
+
+    assert_eq!(event.error_type, "core::num::error::ParseIntError");
+    assert_eq!(event.message, "loading config");
+    let causes = &event.metadata.unwrap()["causes"];
+    assert_eq!(causes.as_array().unwrap().len(), 1);
+    assert_eq!(causes[0]["type"], "core::num::error::ParseIntError");
+}
+
+#[tokio::test]
+async fn test_stack_is_parsed_into_frames() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .in_app_prefixes(["my_app"])
+        .build()
+        .unwrap();
+
+    let stack = "   0: my_app::handlers::checkout::{{closure}}
+             at ./src/handlers/checkout.rs:42:17
+   1: <alloc::boxed::Box<F,A> as core::ops::function::FnOnce<Args>>::call_once
+             at /rustc/abc/library/alloc/src/boxed.rs:1993:9
+   2: __libc_start_main
+";
+    client.capture(Event {
+        error_type: "Checkout".into(),
+        message: "failed".into(),
+        stack: Some(stack.into()),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    let event = recorder.assert_event("Checkout");
+    assert_eq!(event.stack.as_deref(), Some(stack));
+    let frames = event.frames.unwrap();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].module.as_deref(), Some("my_app::handlers"));
+    assert_eq!(frames[0].file.as_deref(), Some("./src/handlers/checkout.rs"));
+    assert_eq!((frames[0].line, frames[0].column), (Some(42), Some(17)));
+    assert!(frames[0].in_app);
+    assert_eq!(frames[1].module.as_deref(), Some("alloc::boxed"));
+    assert!(!frames[1].in_app);
+    assert_eq!(frames[2].function, "__libc_start_main");
+    assert_eq!(frames[2].file, None);
+    assert!(!frames[2].in_app);
+}
+
+#[tokio::test]
+async fn test_captured_backtrace_frames() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    client.capture(Event {
+        error_type: "Backtrace".into(),
+        stack: Some(std::backtrace::Backtrace::force_capture().to_string()),
+        ..Default::default()
+    });
+    client.capture_error("NoStack", "plain");
+    client.flush().await;
+
+    let frames = recorder.assert_event("Backtrace").frames.unwrap();
+    let test_frame = frames
+        .iter()
+        .find(|f| f.function.contains("test_captured_backtrace_frames"))
+        .unwrap();
+    assert!(test_frame.in_app);
+    assert!(test_frame.file.as_deref().unwrap().ends_with("unit_test.rs"));
+    assert!(frames.iter().any(|f| !f.in_app));
+    assert!(recorder.assert_event("NoStack").frames.is_none());
+}
+
+#[tokio::test]
+async fn test_dedup_window_collapses_repeats() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .dedup_window(Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    for _ in 0..5 {
+        client.capture_error("DbTimeout", "query timed out");
+    }
+    client.capture_error("CacheMiss", "key not found");
+    for error_type in ["A", "B"] {
+        client.capture(Event {
+            error_type: error_type.into(),
+            fingerprint: Some("shared".into()),
+            ..Default::default()
+        });
+    }
+    client.flush().await;
+    client.capture_error("DbTimeout", "query timed out");
+    client.flush().await;
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0].error_type, "DbTimeout");
+    assert_eq!(events[0].occurrences, 5);
+    assert_eq!(events[1].occurrences, 1);
+    assert_eq!(events[2].fingerprint, "shared");
+    assert_eq!(events[2].occurrences, 2);
+    // Once uploaded, a repeat starts a new event with the same fingerprint.
+    assert_eq!(events[3].occurrences, 1);
+    assert_eq!(events[3].fingerprint, events[0].fingerprint);
+}
+
+#[tokio::test]
+async fn test_fingerprint_without_dedup() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    client.capture_error("DbTimeout", "first");
+    client.capture_error("DbTimeout", "second");
+    client.capture(Event {
+        error_type: "DbTimeout".into(),
+        route_or_procedure: Some("/orders".into()),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].fingerprint.len(), 64);
+    assert_eq!(events[0].fingerprint, events[1].fingerprint);
+    assert_ne!(events[0].fingerprint, events[2].fingerprint);
+    assert!(events.iter().all(|e| e.occurrences == 1));
+}
+
+#[tokio::test]
+async fn test_rate_limit_per_fingerprint() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .rate_limit(2, Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    for i in 0..5 {
+        client.capture_error("Noisy", format!("attempt {i}"));
+    }
+    client.capture_error("Rare", "only once");
+    client.flush().await;
+
+    assert_eq!(recorder.events_of_type("Noisy").len(), 2);
+    recorder.assert_event("Rare");
+    let summary = recorder.assert_event_message(SUPPRESSED_ERROR_TYPE, "suppressed 3");
+    let suppressed = &summary.metadata.unwrap()["suppressed"];
+    assert_eq!(suppressed.as_array().unwrap().len(), 1);
+    assert_eq!(suppressed[0]["error_type"], "Noisy");
+    assert_eq!(suppressed[0]["count"], 3);
+
+    // Nothing new was suppressed, so the next flush sends no summary.
+    recorder.clear();
+    client.flush().await;
+    recorder.assert_no_events();
+}
+
+#[tokio::test]
+async fn test_rate_limit_summary_is_periodic() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .rate_limit(1, Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    client.capture_error("Noisy", "first");
+    client.capture_error("Noisy", "second");
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    client.capture_error("Noisy", "third");
+    client.flush().await;
+
+    let types: Vec<_> = recorder.events().into_iter().map(|e| e.error_type).collect();
+    assert_eq!(types, ["Noisy", SUPPRESSED_ERROR_TYPE, "Noisy"]);
+}
+
+#[tokio::test]
+async fn test_error_type_sample_rate_overrides_global() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .sample_rate(0.0)
+        .error_type_sample_rate("PaymentFailed", 1.0)
+        .error_type_sample_rate("CacheMiss", 0.0)
+        .build()
+        .unwrap();
+
+    client.capture_error("PaymentFailed", "card declined");
+    client.capture_error("CacheMiss", "key not found");
+    client.capture_error("Other", "dropped by the global rate");
+    client.flush().await;
+
+    assert_eq!(recorder.events().len(), 1);
+    recorder.assert_event("PaymentFailed");
+}
+
+#[tokio::test]
+async fn test_custom_sampler_takes_precedence() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .error_type_sample_rate("Timeout", 0.0)
+        .sampler(|context| match context {
+            SamplingContext::Error(event) if event.message.contains("checkout") => Some(1.0),
+            SamplingContext::Error(event) if event.message.contains("health") => Some(0.0),
+            _ => None,
+        })
+        .build()
+        .unwrap();
+
+    client.capture_error("Timeout", "checkout timed out");
+    client.capture_error("Timeout", "search timed out");
+    client.capture_error("Error", "health probe failed");
+    client.flush().await;
+
+    assert_eq!(recorder.events().len(), 1);
+    recorder.assert_event_message("Timeout", "checkout");
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_trace_sampling_decided_at_start() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .trace_name_sample_rate("healthcheck", 0.0)
+        .sampler(|context| match context {
+            SamplingContext::Trace { name } if name.starts_with("batch-") => Some(1.0),
+            _ => None,
+        })
+        .build()
+        .unwrap();
+
+    let skipped = client.start_trace("healthcheck");
+    assert!(!skipped.sampled);
+    assert_eq!(skipped.sample_rate, Some(0.0));
+    let kept = client.start_trace("chat");
+    assert!(kept.sampled);
+    let batch = client.start_trace("batch-import");
+
+    client.send_trace(skipped);
+    client.send_trace(kept);
+    client.send_trace(batch);
+    client.send_trace(Trace::new("healthcheck"));
+    client.flush().await;
+
+    let traces = recorder.traces();
+    assert_eq!(traces.len(), 2);
+    assert!(traces.iter().all(|t| t.sampled && t.sample_rate == Some(1.0)));
+    let payload = serde_json::to_value(&traces[0]).unwrap();
+    assert_eq!(payload["sampled"], true);
+    assert_eq!(payload["sample_rate"], 1.0);
+}
+
+#[tokio::test]
+async fn test_before_send_event_edits_and_drops() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .before_send_event(|mut event| {
+            if event.error_type == "Ignored" {
+                return None;
+            }
+            if let Some(metadata) = event.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+                metadata.remove("authorization");
+            }
+            event.route_or_procedure = event.route_or_procedure.map(|r| r.replace("/42", "/:id"));
+            Some(event)
+        })
+        .build()
+        .unwrap();
+
+    client.capture_error("Ignored", "noise");
+    client.capture(Event {
+        error_type: "Forbidden".into(),
+        route_or_procedure: Some("/users/42".into()),
+        metadata: Some(serde_json::json!({"authorization": "Bearer x", "method": "GET"})),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    assert_eq!(recorder.events().len(), 1);
+    let event = recorder.assert_event("Forbidden");
+    assert_eq!(event.route_or_procedure.as_deref(), Some("/users/:id"));
+    assert_eq!(event.metadata, Some(serde_json::json!({"method": "GET"})));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_before_send_trace_edits_and_drops() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .before_send_trace(|mut trace| {
+            if trace.name == "internal" {
+                return None;
+            }
+            trace.input = None;
+            Some(trace)
+        })
+        .build()
+        .unwrap();
+
+    client.send_trace(client.start_trace("internal"));
+    client.send_trace(client.start_trace("chat").input_text("secret prompt"));
+    client.flush().await;
+
+    assert_eq!(recorder.traces().len(), 1);
+    assert_eq!(recorder.assert_trace("chat").input, None);
+}
+
+#[test]
+fn test_scrubber_builtin_rules() {
+    let scrubber = Scrubber::new();
+    let mut counts = Redactions::new();
+    let text = scrubber.scrub_text(
+        "mail jane.doe@example.com or call +1 415-555-0100; card 4111 1111 1111 1111, \
+         order 1234 5678 9012 3456, key sk_live_abcdefghijklmnop1234",
+        &mut counts,
+    );
+
+    assert_eq!(
+        text,
+        "mail [REDACTED] or call [REDACTED]; card [REDACTED], \
+         order 1234 5678 9012 3456, key [REDACTED]"
+    );
+    assert_eq!(counts["email"], 1);
+    assert_eq!(counts["phone"], 1);
+    assert_eq!(counts["credit_card"], 1);
+    assert_eq!(counts["api_key"], 1);
+}
+
+#[test]
+fn test_scrubber_phone_rule_needs_formatting() {
+    let scrubber = Scrubber::new();
+    let mut counts = Redactions::new();
+    let text = "job 1760700000 took 3 retries; order 4111111111 ok";
+    assert_eq!(scrubber.scrub_text(text, &mut counts), text);
+    assert!(counts.is_empty());
+
+    let text = scrubber.scrub_text(
+        "+14155550100, +44 20 7946 0958, (415) 555-0100, 415.555.0100",
+        &mut counts,
+    );
+    assert_eq!(text, "[REDACTED], [REDACTED], [REDACTED], [REDACTED]");
+    assert_eq!(counts["phone"], 4);
+}
+
+#[test]
+fn test_scrubber_rejects_invalid_rule() {
+    let result = Scrubber::empty().rule("broken", "(unclosed");
+    assert!(matches!(
+        result,
+        Err(BloopError::InvalidConfig { setting: "scrub rule", .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_scrubber_applied_to_events() {
+    let recorder = RecordingTransport::new();
+    let scrubber = Scrubber::new()
+        .rule("order_id", r"ORD-\d+")
+        .unwrap()
+        .deny_key("X-Session")
+        .replacement("***");
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .scrubber(scrubber)
+        .build()
+        .unwrap();
+
+    client.capture(Event {
+        error_type: "CheckoutFailed".into(),
+        message: "ORD-991 failed for bob@example.com".into(),
+        metadata: Some(serde_json::json!({
+            "Authorization": "Bearer abc",
+            "x_session": "s-1",
+            "notes": ["retry ORD-992"],
+            "attempts": 3,
+        })),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    let event = recorder.assert_event("CheckoutFailed");
+    assert_eq!(event.message, "*** failed for ***");
+    assert_eq!(
+        event.metadata.unwrap(),
+        serde_json::json!({
+            "Authorization": "***",
+            "x_session": "***",
+            "notes": ["retry ***"],
+            "attempts": 3,
+        })
+    );
+    assert_eq!(event.redactions["order_id"], 2);
+    assert_eq!(event.redactions["email"], 1);
+    assert_eq!(event.redactions["denied_key"], 2);
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_scrubber_applied_to_traces() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .scrubber(Scrubber::new())
+        .build()
+        .unwrap();
+
+    let mut trace = client
+        .start_trace("support-chat")
+        .input_text("I'm alice@example.com");
+    let span = trace.start_span(SpanType::Generation, "reply");
+    span.set_output("Call us at (415) 555-0100");
+    trace.end(TraceStatus::Completed);
+    client.send_trace(trace);
+    client.flush().await;
+
+    let trace = recorder.assert_trace("support-chat");
+    assert_eq!(trace.input.as_deref(), Some("I'm [REDACTED]"));
+    assert_eq!(trace.spans[0].output.as_deref(), Some("Call us at [REDACTED]"));
+    assert_eq!(trace.redactions["email"], 1);
+    assert_eq!(trace.redactions["phone"], 1);
+    let payload = serde_json::to_value(&trace).unwrap();
+    assert_eq!(payload["redactions"]["phone"], 1);
+}
+
+#[tokio::test]
+async fn test_hash_user_id() {
+    let build = |key: &str| {
+        BloopClient::builder()
+            .endpoint("https://bloop.example.com")
+            .project_key(key)
+    };
+    let client = build("project-a").user_id_salt("pepper").build().unwrap();
+    assert_eq!(
+        client.hash_user_id("user-42"),
+        "c5ffab518b4d439e7c7a83ff88d3a3ef85f6bbfbf559bbf7dc30de25ff6339cf"
+    );
+
+    // Without a salt the project key is used, so services sharing a
+    // project agree on hashes.
+    let a = build("project-a").build().unwrap();
+    let b = build("project-a").build().unwrap();
+    let other = build("project-b").build().unwrap();
+    assert_eq!(a.hash_user_id("user-42"), b.hash_user_id("user-42"));
+    assert_ne!(a.hash_user_id("user-42"), other.hash_user_id("user-42"));
+
+    // Never the request signature of the same bytes under the project key.
+    use hmac::Mac;
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(b"project-a").unwrap();
+    mac.update(b"user-42");
+    let signature = hex::encode(mac.finalize().into_bytes());
+    assert_ne!(a.hash_user_id("user-42"), signature);
+
+    let event = Event::default().hashed_user_id(&a, "user-42");
+    assert_eq!(event.user_id_hash, Some(a.hash_user_id("user-42")));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_trace_hashed_user_id() {
+    let client = BloopClient::builder()
+        .endpoint("https://bloop.example.com")
+        .project_key("project-a")
+        .build()
+        .unwrap();
+    let trace = client.start_trace("chat").hashed_user_id(&client, "user-42");
+    assert_eq!(trace.user_id, Some(client.hash_user_id("user-42")));
+    assert_ne!(trace.user_id.as_deref(), Some("user-42"));
+}
+
+#[tokio::test]
+async fn test_global_scope_merged_into_events() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .release("1.0.0")
+        .build()
+        .unwrap();
+
+    client.configure_scope(|scope| {
+        scope.set_tag("region", "eu-west-1");
+        scope.set_extra("pod", "api-7");
+        scope.set_user_id_hash("hash-1");
+        scope.set_request_id("req-1");
+        scope.set_release("1.0.1-canary");
+    });
+    client.capture_error("Plain", "uses the scope");
+    client.capture(Event {
+        error_type: "Explicit".into(),
+        request_id: Some("req-own".into()),
+        metadata: Some(serde_json::json!({"pod": "own", "tags": {"region": "own"}})),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    let plain = recorder.assert_event("Plain");
+    assert_eq!(plain.release, "1.0.1-canary");
+    assert_eq!(plain.user_id_hash.as_deref(), Some("hash-1"));
+    assert_eq!(plain.request_id.as_deref(), Some("req-1"));
+    assert_eq!(
+        plain.metadata,
+        Some(serde_json::json!({"pod": "api-7", "tags": {"region": "eu-west-1"}}))
+    );
+
+    let explicit = recorder.assert_event("Explicit");
+    assert_eq!(explicit.request_id.as_deref(), Some("req-own"));
+    assert_eq!(
+        explicit.metadata,
+        Some(serde_json::json!({"pod": "own", "tags": {"region": "own"}}))
+    );
+}
+
+#[tokio::test]
+async fn test_with_scope_layers_and_pops() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+    client.configure_scope(|scope| scope.set_tag("service", "api"));
+
+    client.with_scope(
+        |scope| scope.set_route_or_procedure("/orders"),
+        || {
+            client.with_scope(
+                |scope| scope.set_tag("step", "charge"),
+                || client.capture_error("Inner", "nested"),
+            );
+            client.capture_error("Outer", "one level");
+        },
+    );
+    client.capture_error("After", "scope popped");
+    client.flush().await;
+
+    let inner = recorder.assert_event("Inner");
+    assert_eq!(inner.route_or_procedure.as_deref(), Some("/orders"));
+    assert_eq!(
+        inner.metadata.unwrap()["tags"],
+        serde_json::json!({"service": "api", "step": "charge"})
+    );
+    let outer = recorder.assert_event("Outer");
+    assert_eq!(outer.route_or_procedure.as_deref(), Some("/orders"));
+    assert_eq!(outer.metadata.unwrap()["tags"], serde_json::json!({"service": "api"}));
+    assert_eq!(recorder.assert_event("After").route_or_procedure, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_scope_follows_the_future() {
+    let recorder = RecordingTransport::new();
+    let client = std::sync::Arc::new(recorder.client());
+
+    let task = {
+        let client = client.clone();
+        tokio::spawn(async move {
+            client
+                .with_task_scope(
+                    |scope| scope.set_request_id("req-task"),
+                    async {
+                        tokio::task::yield_now().await;
+                        client.capture_error("InTask", "after an await");
+                    },
+                )
+                .await
+        })
+    };
+    task.await.unwrap();
+    client.capture_error("Outside", "no task scope");
+    client.flush().await;
+
+    let in_task = recorder.assert_event("InTask");
+    assert_eq!(in_task.request_id.as_deref(), Some("req-task"));
+    assert_eq!(recorder.assert_event("Outside").request_id, None);
+}
+
+#[tokio::test]
+async fn test_breadcrumbs_attached_to_events() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .max_breadcrumbs(3)
+        .build()
+        .unwrap();
+
+    for i in 0..5 {
+        client.add_breadcrumb(Breadcrumb::new("http", format!("GET /items/{i}")));
+    }
+    client.add_breadcrumb(
+        Breadcrumb::new("db", "slow query")
+            .level(Level::Warning)
+            .data(serde_json::json!({"ms": 1200})),
+    );
+    client.capture_error("Failed", "after some work");
+    client.flush().await;
+
+    let event = recorder.assert_event("Failed");
+    let messages: Vec<_> = event.breadcrumbs.iter().map(|b| b.message.as_str()).collect();
+    assert_eq!(messages, ["GET /items/3", "GET /items/4", "slow query"]);
+
+    let payload = serde_json::to_value(&event).unwrap();
+    let crumb = &payload["breadcrumbs"][2];
+    assert_eq!(crumb["category"], "db");
+    assert_eq!(crumb["level"], "warning");
+    assert_eq!(crumb["data"]["ms"], 1200);
+    assert!(crumb["timestamp"].as_i64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_scoped_breadcrumbs_stay_in_scope() {
+    let recorder = RecordingTransport::new();
+    let client = recorder.client();
+
+    client.add_breadcrumb(Breadcrumb::new("app", "started"));
+    client.with_scope(
+        |_| {},
+        || {
+            client.add_breadcrumb(Breadcrumb::new("job", "step 1"));
+            client.capture_error("InScope", "job failed");
+        },
+    );
+    client
+        .with_task_scope(|_| {}, async {
+            client.add_breadcrumb(Breadcrumb::new("request", "GET /"));
+            client.capture_error("InTask", "request failed");
+        })
+        .await;
+    client.capture_error("Global", "no local crumbs");
+    client.flush().await;
+
+    let categories = |error_type: &str| -> Vec<String> {
+        recorder
+            .assert_event(error_type)
+            .breadcrumbs
+            .into_iter()
+            .map(|b| b.category)
+            .collect()
+    };
+    assert_eq!(categories("InScope"), ["app", "job"]);
+    assert_eq!(categories("InTask"), ["app", "request"]);
+    assert_eq!(categories("Global"), ["app"]);
+}
+
+#[tokio::test]
+async fn test_levels_and_min_level() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .min_level(Level::Warning)
+        .build()
+        .unwrap();
+
+    client.capture_message(Level::Debug, "cache warmed");
+    client.capture_message(Level::Info, "user signed in");
+    client.capture_message(Level::Warning, "disk 90% full");
+    client.capture_error("DbError", "connection reset");
+    client.capture(Event {
+        error_type: "Corruption".into(),
+        level: Some(Level::Fatal),
+        ..Default::default()
+    });
+    client.flush().await;
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 3);
+    let warning = recorder.assert_event_message(MESSAGE_ERROR_TYPE, "disk");
+    assert_eq!(warning.level, Level::Warning);
+    assert_eq!(recorder.assert_event("DbError").level, Level::Error);
+    assert_eq!(recorder.assert_event("Corruption").level, Level::Fatal);
+
+    let payload = serde_json::to_value(&warning).unwrap();
+    assert_eq!(payload["level"], "warning");
+}
+
+#[tokio::test]
+async fn test_messages_deduplicated_by_text() {
+    let recorder = RecordingTransport::new();
+    let client = BloopClient::builder()
+        .transport(recorder.clone())
+        .dedup_window(Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    client.capture_message(Level::Info, "retrying upload");
+    client.capture_message(Level::Info, "retrying upload");
+    client.capture_message(Level::Info, "upload abandoned");
+    client.flush().await;
+
+    let events = recorder.events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].occurrences, 2);
+    assert_eq!(events[1].message, "upload abandoned");
+}
+
+#[test]
+fn test_level_ordering_and_serialization() {
+    assert!(Level::Debug < Level::Info);
+    assert!(Level::Warning < Level::Error);
+    assert!(Level::Error < Level::Fatal);
+    assert_eq!(serde_json::to_value(Level::Fatal).unwrap(), "fatal");
+
+    let event = Event {
+        error_type: "Error".into(),
+        ..Default::default()
+    };
+    assert!(!serde_json::to_string(&event).unwrap().contains("level"));
+}